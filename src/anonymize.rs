@@ -0,0 +1,296 @@
+//! Anonymizing client logs for the `anonymize` command
+//!
+//! Replaces IPv4 and IPv6 addresses, host names, quoted names (worlds, servers), user
+//! names, UUIDs and home directory user names with placeholders. The same
+//! value always gets the same placeholder, and only the message part of a
+//! line is touched, so the anonymized log still parses the same way.
+
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+
+use anyhow::{Context, Result};
+use chrono::{Local, TimeZone};
+use regex::{Captures, Regex};
+
+use crate::log_watcher::LogWatcher;
+use crate::state_machine::Transition;
+
+/// Addresses that say where the game connects, but not to whom
+const LOOPBACK: &[&str] = &["127.0.0.1", "0.0.0.0", "localhost", "::1", "::"];
+
+/// Endings of file names that would otherwise look like host names
+const FILE_EXTENSIONS: &[&str] = &[
+    "bak", "bin", "cfg", "cs", "dat", "dll", "dmp", "exe", "ini", "jpg", "json", "lang", "log",
+    "mdmp", "ogg", "png", "so", "tmp", "txt", "wav", "xml", "zip",
+];
+
+/// Domain of the host placeholders; it is reserved, so never a real server
+const PLACEHOLDER_DOMAIN: &str = ".example.com";
+
+struct Patterns {
+    uuid: Regex,
+    home: Regex,
+    user: Regex,
+    quoted: Regex,
+    ip: Regex,
+    ipv6: Regex,
+    host_port: Regex,
+    host: Regex,
+}
+
+impl Patterns {
+    fn new() -> Self {
+        Self {
+            uuid: Regex::new(
+                r"(?i)\b[0-9a-f]{8}-[0-9a-f]{4}-[0-9a-f]{4}-[0-9a-f]{4}-[0-9a-f]{12}\b",
+            )
+            .unwrap(),
+            home: Regex::new(r#"(/home/|/var/home/|/Users/|[A-Za-z]:\\Users\\)([^/\\\s"']+)"#)
+                .unwrap(),
+            user: Regex::new(
+                r#"(?i)\b(user(?:name)?|player(?:name)?|account|profile)(\s*[:=]\s*)([^\s,;"']+)"#,
+            )
+            .unwrap(),
+            quoted: Regex::new(r#""([^"]+)""#).unwrap(),
+            ip: Regex::new(r"\b\d{1,3}(?:\.\d{1,3}){3}\b").unwrap(),
+            // In brackets (as with a port), or bare when written out in full
+            // or with "::", so times like 11:06:22 don't match
+            ipv6: Regex::new(
+                r"(?i)\[((?:[0-9a-f]{0,4}:){2,7}[0-9a-f]{0,4})\]|\b((?:[0-9a-f]{1,4}:){7}[0-9a-f]{1,4}|(?:[0-9a-f]{1,4}:){1,6}:(?:[0-9a-f]{1,4}:){0,5}[0-9a-f]{1,4})\b",
+            )
+            .unwrap(),
+            host_port: Regex::new(r"\b([A-Za-z0-9][\w-]*(?:\.[\w-]+)*\.[A-Za-z][\w-]*):(\d+)\b")
+                .unwrap(),
+            // Lowercase only, so dotted type names like System.IO.IOException
+            // are left alone
+            host: Regex::new(r"\b(?:[a-z0-9](?:[a-z0-9-]*[a-z0-9])?\.)+([a-z]{2,24})\b").unwrap(),
+        }
+    }
+}
+
+/// Rewrites log lines with consistent placeholders
+pub struct Anonymizer {
+    patterns: Patterns,
+    /// Placeholder of each value seen so far, by kind of value
+    seen: HashMap<(&'static str, String), String>,
+    /// Number of distinct values of each kind
+    counts: HashMap<&'static str, usize>,
+}
+
+impl Anonymizer {
+    /// Create an anonymizer with no values seen yet
+    pub fn new() -> Self {
+        Self {
+            patterns: Patterns::new(),
+            seen: HashMap::new(),
+            counts: HashMap::new(),
+        }
+    }
+
+    /// Number of distinct values replaced so far
+    pub fn replaced(&self) -> usize {
+        self.seen.len()
+    }
+
+    /// Anonymize one log line, keeping its timestamp, level and source
+    pub fn line(&mut self, raw_line: &str) -> String {
+        let parts: Vec<&str> = raw_line.splitn(4, '|').collect();
+        match parts[..] {
+            [timestamp, level, source, message] => format!(
+                "{}|{}|{}|{}",
+                timestamp,
+                level,
+                source,
+                self.message(message)
+            ),
+            _ => self.message(raw_line),
+        }
+    }
+
+    fn message(&mut self, message: &str) -> String {
+        let Self {
+            patterns,
+            seen,
+            counts,
+        } = self;
+        let mut placeholder = |kind: &'static str, value: &str, format: fn(usize) -> String| {
+            seen.entry((kind, value.to_string()))
+                .or_insert_with(|| {
+                    let count = counts.entry(kind).or_default();
+                    *count += 1;
+                    format(*count)
+                })
+                .clone()
+        };
+
+        let message = patterns.uuid.replace_all(message, |caps: &Captures| {
+            placeholder("uuid", &caps[0], |n| {
+                format!("00000000-0000-0000-0000-{:012}", n)
+            })
+        });
+        let message = patterns.home.replace_all(&message, |caps: &Captures| {
+            let user = placeholder("user", &caps[2], |n| format!("user{}", n));
+            format!("{}{}", &caps[1], user)
+        });
+        let message = patterns.user.replace_all(&message, |caps: &Captures| {
+            let user = placeholder("user", &caps[3], |n| format!("user{}", n));
+            format!("{}{}{}", &caps[1], &caps[2], user)
+        });
+        let message = patterns.quoted.replace_all(&message, |caps: &Captures| {
+            format!("\"{}\"", placeholder("name", &caps[1], |n| format!("Name {}", n)))
+        });
+        let message = patterns.ipv6.replace_all(&message, |caps: &Captures| {
+            let (address, bracketed) = match caps.get(1) {
+                Some(m) => (m.as_str(), true),
+                None => (&caps[2], false),
+            };
+            if LOOPBACK.contains(&address) {
+                return caps[0].to_string();
+            }
+            let address = placeholder("ipv6", address, |n| format!("2001:db8::{:x}", n));
+            if bracketed {
+                format!("[{}]", address)
+            } else {
+                address
+            }
+        });
+        let message = patterns.ip.replace_all(&message, |caps: &Captures| {
+            if LOOPBACK.contains(&&caps[0]) {
+                return caps[0].to_string();
+            }
+            placeholder("ip", &caps[0], |n| {
+                format!("10.{}.{}.{}", n >> 16 & 0xff, n >> 8 & 0xff, n & 0xff)
+            })
+        });
+        let message = patterns.host_port.replace_all(&message, |caps: &Captures| {
+            if LOOPBACK.contains(&&caps[1]) {
+                return caps[0].to_string();
+            }
+            let host = placeholder("host", &caps[1], |n| format!("server{}{}", n, PLACEHOLDER_DOMAIN));
+            format!("{}:{}", host, &caps[2])
+        });
+        let message = patterns.host.replace_all(&message, |caps: &Captures| {
+            let host = &caps[0];
+            if LOOPBACK.contains(&host)
+                || host.ends_with(PLACEHOLDER_DOMAIN)
+                || FILE_EXTENSIONS.contains(&&caps[1])
+            {
+                return host.to_string();
+            }
+            placeholder("host", host, |n| format!("server{}{}", n, PLACEHOLDER_DOMAIN))
+        });
+        message.into_owned()
+    }
+}
+
+impl Default for Anonymizer {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Anonymize the log at `input` into `output`. Returns the number of lines
+/// and of distinct values replaced.
+pub fn anonymize_file(input: &Path, output: &Path) -> Result<(usize, usize)> {
+    let bytes = fs::read(input).with_context(|| format!("Failed to read {}", input.display()))?;
+    let text = String::from_utf8_lossy(&bytes);
+
+    let mut anonymizer = Anonymizer::new();
+    let mut out = String::with_capacity(text.len());
+    let mut lines = 0;
+    for line in text.lines() {
+        out.push_str(&anonymizer.line(line));
+        out.push('\n');
+        lines += 1;
+    }
+
+    fs::write(output, out).with_context(|| format!("Failed to write {}", output.display()))?;
+    Ok((lines, anonymizer.replaced()))
+}
+
+/// Replay a log and render the states it goes through, see [`render_timeline`]
+pub fn timeline(path: &Path, names: bool) -> Result<Vec<String>> {
+    let mut watcher = LogWatcher::new();
+    watcher.replay(path)?;
+    Ok(render_timeline(&watcher.drain_transitions(), names))
+}
+
+/// One line per transition: when it happened, its cause and the state it led
+/// to. With `names`, the state is serialized in full; without, only its kind
+/// and how it is shown, so anonymized logs render the same.
+pub fn render_timeline(transitions: &[Transition], names: bool) -> Vec<String> {
+    transitions
+        .iter()
+        .map(|t| {
+            let at = Local
+                .timestamp_opt(t.at, 0)
+                .single()
+                .map(|at| at.format("%Y-%m-%d %H:%M:%S").to_string())
+                .unwrap_or_else(|| t.at.to_string());
+            let cause = serde_json::to_value(t.cause).unwrap_or_default();
+            let state = if names {
+                serde_json::to_string(&t.to).unwrap_or_default()
+            } else {
+                format!("{} ({})", t.to.kind(), t.to.details())
+            };
+            format!("{}  {}  {}", at, cause.as_str().unwrap_or_default(), state)
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_line() {
+        let mut anonymizer = Anonymizer::new();
+        assert_eq!(
+            anonymizer.line(
+                "2026-02-07 21:03:40.0542|INFO|HytaleClient.Networking.Connection|Opening Quic Connection to play.example.net:5520"
+            ),
+            "2026-02-07 21:03:40.0542|INFO|HytaleClient.Networking.Connection|Opening Quic Connection to server1.example.com:5520"
+        );
+        assert_eq!(
+            anonymizer.line(r#"2026-02-07 21:03:41.0000|INFO|Auth|Logged in as username=Steve (uuid 3f2a1c9e-7b4d-4e8f-9a01-2b3c4d5e6f70)"#),
+            "2026-02-07 21:03:41.0000|INFO|Auth|Logged in as username=user1 (uuid 00000000-0000-0000-0000-000000000001)"
+        );
+        assert_eq!(
+            anonymizer.line(r#"Saving "My World" to /home/steve/.hytale/Saves, ping 203.0.113.7"#),
+            r#"Saving "Name 1" to /home/user2/.hytale/Saves, ping 10.0.0.1"#
+        );
+
+        // Same values get the same placeholders, loopback is kept
+        assert_eq!(
+            anonymizer.line(r#"Connecting to singleplayer world "My World" via 127.0.0.1:5520 and play.example.net:5520"#),
+            r#"Connecting to singleplayer world "Name 1" via 127.0.0.1:5520 and server1.example.com:5520"#
+        );
+        assert_eq!(anonymizer.replaced(), 6);
+
+        // Bare host names get the same placeholder as with a port
+        assert_eq!(
+            anonymizer.line("Resolved play.example.net, saved to client.log"),
+            "Resolved server1.example.com, saved to client.log"
+        );
+        assert_eq!(
+            anonymizer.line("Resolving mc.other-host.org failed: System.Net.Sockets.SocketException"),
+            "Resolving server2.example.com failed: System.Net.Sockets.SocketException"
+        );
+    }
+
+    #[test]
+    fn test_ipv6() {
+        let mut anonymizer = Anonymizer::new();
+        assert_eq!(
+            anonymizer.line(
+                "2026-02-07 21:03:40.0542|INFO|HytaleClient.Networking.Connection|Opening Quic Connection to [2a01:4f8:c17:1a2b::1]:5520"
+            ),
+            "2026-02-07 21:03:40.0542|INFO|HytaleClient.Networking.Connection|Opening Quic Connection to [2001:db8::1]:5520"
+        );
+        assert_eq!(
+            anonymizer.line("Ping to 2a01:4f8:c17:1a2b::1 at 11:06:22, local [::1]:5520"),
+            "Ping to 2001:db8::1 at 11:06:22, local [::1]:5520"
+        );
+    }
+}
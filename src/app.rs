@@ -0,0 +1,625 @@
+//! The application: runs the poll loop and drives every output
+//!
+//! Polls the process list and log file, keeps the tray and D-Bus service up
+//! to date and feeds the current state to the enabled sinks.
+
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use anyhow::Result;
+use log::{debug, error, info, warn};
+
+use crate::capture::REPORT_FILE;
+use crate::config::{get_data_dir, AppConfig, GameState, POLL_INTERVAL_MS};
+use crate::crash::{self, CrashBundle};
+#[cfg(all(feature = "dbus", target_os = "linux"))]
+use crate::dbus_service::{ControlCommand, DbusService};
+use crate::events::{self, Event, ProcessKind};
+use crate::file_output::FileOutput;
+use crate::health::Counts;
+use crate::history::{SessionHistory, SessionRecord};
+use crate::http::StatusServer;
+use crate::log_watcher::{unix_now, LogWatcher};
+use crate::metrics;
+use crate::mqtt::MqttSink;
+use crate::process::{DetectedProcess, ProcessDetector};
+use crate::resources::{ResourceStats, ResourceUsage};
+#[cfg(feature = "discord")]
+use crate::rpc::DiscordRpc;
+use crate::sink::{PresenceUpdate, SinkSet};
+use crate::state_machine::{Cause, Transition};
+use crate::tray::{open_url, show_notification, SystemTray, TrayEvent, TrayStatus};
+use crate::webhook::WebhookSink;
+
+/// Most state changes kept for a crash report
+const MAX_TIMELINE: usize = 100;
+
+/// Most entries in the tray's health submenu
+const MAX_TRAY_HEALTH_ENTRIES: usize = 15;
+
+/// Longest health entry shown in the tray before it is cut off
+const MAX_TRAY_ENTRY_CHARS: usize = 80;
+
+/// Least time between two rewrites of the unknown-lines report
+const CAPTURE_WRITE_INTERVAL: Duration = Duration::from_secs(10);
+
+/// Application state
+pub struct App {
+    process_detector: ProcessDetector,
+    log_watcher: LogWatcher,
+    sinks: SinkSet,
+    tray: Option<SystemTray>,
+    #[cfg(all(feature = "dbus", target_os = "linux"))]
+    dbus: Option<DbusService>,
+    config: Arc<Mutex<AppConfig>>,
+    /// Outputs are hidden and not updated while paused
+    paused: bool,
+    hytale_was_running: bool,
+    launcher_was_running: bool,
+    /// Unix timestamp (seconds) at which the running game client started
+    game_started_at: Option<i64>,
+    game_process: Option<DetectedProcess>,
+    /// State changes since the game client started
+    timeline: Vec<Transition>,
+    /// Warning and error counts last shown in the tray
+    tray_health: Counts,
+    resource_stats: ResourceStats,
+    history: SessionHistory,
+    /// Where to write the report of unmatched log messages
+    capture_path: Option<PathBuf>,
+    /// Message count and time of the last report written
+    capture_written: (u64, Option<Instant>),
+}
+
+/// Create the outputs enabled in the config
+fn build_sinks(config: &AppConfig) -> SinkSet {
+    let mut sinks = SinkSet::new();
+
+    #[cfg(feature = "discord")]
+    if config.discord.enabled {
+        sinks.push(Box::new(DiscordRpc::new()));
+    }
+
+    if config.http.enabled {
+        match StatusServer::start(&config.http) {
+            Ok(server) => {
+                info!("HTTP status server listening on http://{}", server.local_addr());
+                sinks.push(Box::new(server));
+            }
+            Err(e) => warn!("Failed to start HTTP status server: {}", e),
+        }
+    }
+
+    if config.file_output.enabled {
+        sinks.push(Box::new(FileOutput::new()));
+    }
+
+    if config.webhook.enabled {
+        sinks.push(Box::new(WebhookSink::new()));
+    }
+
+    if config.mqtt.enabled {
+        sinks.push(Box::new(MqttSink::new(&config.mqtt)));
+    }
+
+    sinks
+}
+
+impl App {
+    /// Load the config and create the enabled outputs
+    pub fn new() -> Result<Self> {
+        let config = AppConfig::load();
+        let sinks = build_sinks(&config);
+
+        #[cfg(all(feature = "dbus", target_os = "linux"))]
+        let dbus = if config.dbus.enabled {
+            DbusService::start()
+                .map_err(|e| warn!("Failed to start D-Bus service: {:#}", e))
+                .ok()
+        } else {
+            None
+        };
+
+        let mut log_watcher = LogWatcher::new();
+        log_watcher.subscribe(|transition| {
+            debug!(
+                "State: {} -> {} ({:?})",
+                transition.from.kind(),
+                transition.to.kind(),
+                transition.cause
+            );
+            events::emit(Event::from(transition));
+            metrics::global().enter_state(transition.to.kind(), Instant::now());
+        });
+        metrics::global().enter_state(GameState::Unknown.kind(), Instant::now());
+
+        Ok(Self {
+            process_detector: ProcessDetector::new(),
+            log_watcher,
+            sinks,
+            tray: None,
+            #[cfg(all(feature = "dbus", target_os = "linux"))]
+            dbus,
+            config: Arc::new(Mutex::new(config)),
+            paused: false,
+            hytale_was_running: false,
+            launcher_was_running: false,
+            game_started_at: None,
+            game_process: None,
+            timeline: Vec::new(),
+            tray_health: Counts::default(),
+            resource_stats: ResourceStats::new(),
+            history: SessionHistory::new(),
+            capture_path: None,
+            capture_written: (0, None),
+        })
+    }
+
+    /// Show the system tray icon
+    pub fn init_tray(&mut self) -> Result<()> {
+        match SystemTray::new(self.config.clone()) {
+            Ok(tray) => {
+                self.tray = Some(tray);
+                info!("System tray initialized successfully");
+            }
+            Err(e) => {
+                warn!("Failed to initialize system tray: {}. Running in CLI mode.", e);
+            }
+        }
+        Ok(())
+    }
+
+    /// Record log messages that no pattern matches and write them to `path`
+    /// (default: unknown-lines.txt in the data directory)
+    pub fn capture_unknown(&mut self, path: Option<PathBuf>) {
+        let path = path.unwrap_or_else(|| get_data_dir().join(REPORT_FILE));
+        info!("Capturing unmatched log messages to {}", path.display());
+        self.log_watcher.enable_capture();
+        self.capture_path = Some(path);
+    }
+
+    /// Write the unknown-lines report if it changed, at most every
+    /// [`CAPTURE_WRITE_INTERVAL`] unless `force`d
+    fn write_capture(&mut self, force: bool) {
+        let (Some(path), Some(capture)) = (&self.capture_path, self.log_watcher.capture()) else {
+            return;
+        };
+        let (written, at) = self.capture_written;
+        if capture.total() == written
+            || (!force && at.is_some_and(|at| at.elapsed() < CAPTURE_WRITE_INTERVAL))
+        {
+            return;
+        }
+
+        match capture.write_report(path) {
+            Ok(()) => debug!("Wrote {} unmatched log lines to {}", capture.total(), path.display()),
+            Err(e) => warn!("Failed to write unmatched log lines: {:#}", e),
+        }
+        self.capture_written = (capture.total(), Some(Instant::now()));
+    }
+
+    /// Tell a crash from a clean exit and clear everything from the session
+    fn handle_game_exit(&mut self) {
+        // Pick up whatever the game wrote right before exiting
+        if let Err(e) = self.log_watcher.read_remaining() {
+            debug!("Could not read the end of the log: {}", e);
+        }
+        let since = self.game_started_at.unwrap_or_else(unix_now);
+        self.log_watcher.game_exited();
+        self.publish(&GameState::Unknown, false, None);
+        self.write_capture(true);
+        let dumps = match self.log_watcher.current_log_path().and_then(Path::parent) {
+            Some(log_dir) => {
+                let mut dirs = vec![log_dir];
+                dirs.extend(log_dir.parent());
+                crash::find_dumps(&dirs, since)
+            }
+            None => Vec::new(),
+        };
+        let analysis = crash::analyze(
+            &self.log_watcher.recent_lines(),
+            self.log_watcher.shutdown_seen(),
+            dumps,
+        );
+
+        events::emit(Event::ProcessClosed {
+            process: ProcessKind::Game,
+        });
+        if analysis.crashed {
+            let headline = analysis.headline.as_deref().unwrap_or("no error message");
+            warn!("Hytale Game crashed: {}", headline);
+            let bundle = CrashBundle {
+                created_at: unix_now(),
+                headline: analysis.headline.clone(),
+                reasons: analysis.reasons,
+                dumps: analysis.dumps,
+                process: self.game_process.take(),
+                log_path: self.log_watcher.current_log_path().map(Path::to_path_buf),
+                timeline: std::mem::take(&mut self.timeline),
+                last_lines: self.log_watcher.recent_lines(),
+            };
+            let saved = match bundle.save(&get_data_dir().join(crash::CRASH_DIR)) {
+                Ok(path) => {
+                    info!("Saved crash report to {}", path.display());
+                    Some(path)
+                }
+                Err(e) => {
+                    warn!("Failed to save crash report: {:#}", e);
+                    None
+                }
+            };
+            events::emit(Event::GameCrashed {
+                headline: analysis.headline.clone(),
+                bundle: saved,
+            });
+            show_notification("Hytale Game crashed", headline);
+        } else {
+            if analysis.reasons.is_empty() {
+                info!("Hytale Game closed");
+            } else {
+                warn!("Hytale Game closed ({})", analysis.reasons.join(", "));
+            }
+            show_notification("Hytale RPC", "Hytale Game closed");
+        }
+
+        self.update_tray_status("Waiting for Hytale...");
+        self.log_watcher.reset();
+        self.sinks.clear(&self.config.lock().unwrap());
+        // Normally recorded on the GameExited transition, unless the log
+        // never left the unknown state
+        self.record_game_session(unix_now());
+        self.game_process = None;
+        self.timeline.clear();
+    }
+
+    /// Pass the state and the transitions since the last call to the
+    /// session history, the D-Bus service and the sinks, which get them on
+    /// resuming when paused.
+    /// Returns whether there were any transitions.
+    fn publish(&mut self, state: &GameState, game_running: bool, usage: Option<ResourceUsage>) -> bool {
+        let transitions = self.log_watcher.drain_transitions();
+        self.push_timeline(transitions.iter().cloned());
+        if let Some(exit) = transitions.iter().find(|t| t.cause == Cause::GameExited) {
+            self.record_game_session(exit.at);
+        }
+        let session_start = self.log_watcher.session_start();
+
+        #[cfg(all(feature = "dbus", target_os = "linux"))]
+        if let Some(ref mut dbus) = self.dbus {
+            dbus.update(state, session_start, self.paused, &self.config.lock().unwrap());
+        }
+
+        if self.paused {
+            // Delivered with the first update after resuming
+            self.sinks.hold(&transitions, &self.config.lock().unwrap());
+        } else {
+            let config_guard = self.config.lock().unwrap();
+            self.sinks.update(&PresenceUpdate {
+                state,
+                session_start,
+                game_running,
+                game_started_at: self.game_started_at.filter(|_| game_running),
+                transitions: &transitions,
+                resources: usage,
+                health: self.log_watcher.health(),
+                config: &config_guard,
+            });
+        }
+        !transitions.is_empty()
+    }
+
+    /// Remember state changes for crash reports
+    fn push_timeline(&mut self, transitions: impl IntoIterator<Item = Transition>) {
+        self.timeline.extend(transitions);
+        let excess = self.timeline.len().saturating_sub(MAX_TIMELINE);
+        self.timeline.drain(..excess);
+    }
+
+    /// Append the game session that ended at `end` to the history
+    fn record_game_session(&mut self, end: i64) {
+        let Some(start) = self.game_started_at.take() else {
+            return;
+        };
+        if !self.config.lock().unwrap().resources.history {
+            return;
+        }
+
+        let record = SessionRecord {
+            start,
+            end,
+            resources: self.resource_stats.summary(),
+        };
+        if let Err(e) = self.history.append(&record) {
+            warn!("Failed to record session history: {:#}", e);
+        }
+    }
+
+    fn update_tray_status(&self, tooltip: &str) {
+        if self.paused {
+            return;
+        }
+        if let Some(ref tray) = self.tray {
+            let health = self.log_watcher.health();
+            let mut entries = health.report_lines();
+            entries.truncate(MAX_TRAY_HEALTH_ENTRIES);
+            for entry in &mut entries {
+                if entry.chars().count() > MAX_TRAY_ENTRY_CHARS {
+                    *entry = entry.chars().take(MAX_TRAY_ENTRY_CHARS).collect::<String>() + "…";
+                }
+            }
+            tray.update_status(TrayStatus {
+                tooltip: tooltip.to_string(),
+                health_label: format!("Health: {}", health.total.summary()),
+                health: entries,
+            });
+        } else {
+            // CLI mode - print to console
+            // println!("[Status] {}", tooltip); // Reduce noise
+        }
+    }
+
+    fn handle_tray_events(&mut self) -> bool {
+        if let Some(ref tray) = self.tray {
+            while let Some(event) = tray.poll_event() {
+                match event {
+                    TrayEvent::Quit => {
+                        info!("Quit requested from tray");
+                        return true;
+                    }
+                    TrayEvent::OpenGithub => {
+                        open_url("https://github.com/MopigamesYT/hytale-rpc-rs");
+                    }
+                    TrayEvent::OpenHytale => {
+                        open_url("https://hytale.com");
+                    }
+                    TrayEvent::ToggleShowWorldName => {
+                        let value = !self.config.lock().unwrap().show_world_name;
+                        self.set_option("show_world_name", value);
+                    }
+                    TrayEvent::ToggleShowServerIp => {
+                        let value = !self.config.lock().unwrap().show_server_ip;
+                        self.set_option("show_server_ip", value);
+                    }
+                }
+            }
+        }
+        false
+    }
+
+    #[cfg(all(feature = "dbus", target_os = "linux"))]
+    fn handle_dbus_commands(&mut self) {
+        while let Some(command) = self.dbus.as_ref().and_then(|dbus| dbus.poll_command()) {
+            match command {
+                ControlCommand::Pause => self.set_paused(true),
+                ControlCommand::Resume => self.set_paused(false),
+                ControlCommand::SetOption { name, value } => self.set_option(&name, value),
+            }
+        }
+    }
+
+    /// Change a privacy option, save the config and refresh the tray menu
+    fn set_option(&self, name: &str, value: bool) {
+        {
+            let mut cfg = self.config.lock().unwrap();
+            match name {
+                "show_world_name" => cfg.show_world_name = value,
+                "show_server_ip" => cfg.show_server_ip = value,
+                _ => {
+                    warn!("Unknown option: {}", name);
+                    return;
+                }
+            }
+            if let Err(e) = cfg.save() {
+                error!("Failed to save config: {}", e);
+            }
+        }
+        info!("Set {} to {}", name, value);
+        events::emit(Event::ConfigChanged {
+            option: name.to_string(),
+            value,
+        });
+
+        // Refresh menu to show new state
+        #[cfg(target_os = "linux")]
+        if let Some(ref tray) = self.tray {
+            tray.refresh_menu();
+        }
+    }
+
+    /// Pause or resume all outputs (also available over D-Bus)
+    pub fn set_paused(&mut self, paused: bool) {
+        if self.paused == paused {
+            return;
+        }
+
+        if paused {
+            info!("Presence paused");
+            self.sinks.pause(&self.config.lock().unwrap());
+            self.update_tray_status("Paused");
+            self.paused = true;
+        } else {
+            info!("Presence resumed");
+            self.paused = false;
+            let status = if self.hytale_was_running {
+                let state = self.log_watcher.state();
+                format!("{} - {}", state.details(), state.state(&self.config.lock().unwrap()))
+            } else if self.launcher_was_running {
+                "In Launcher".to_string()
+            } else {
+                "Waiting for Hytale...".to_string()
+            };
+            self.update_tray_status(&status);
+        }
+    }
+
+    /// Run the poll loop until quit is requested from the tray
+    pub fn run(&mut self) -> Result<()> {
+        info!("Starting Hytale Discord Rich Presence");
+        self.update_tray_status("Waiting for Hytale...");
+
+        loop {
+            let loop_start = Instant::now();
+
+            // Handle tray events
+            if self.handle_tray_events() {
+                break;
+            }
+            #[cfg(all(feature = "dbus", target_os = "linux"))]
+            self.handle_dbus_commands();
+
+            // Refresh process list
+            self.process_detector.refresh();
+
+            let game_running = self.process_detector.is_game_running();
+            let launcher_running = self.process_detector.is_launcher_running();
+
+            // Handle Hytale Game state changes. A newer client taking over
+            // (e.g. a quick restart) starts a new session too, so resource
+            // stats never mix two processes.
+            let game_process = self.process_detector.game_process();
+            let replaced = self.hytale_was_running
+                && game_process.as_ref().map(|p| (p.pid, p.start_time))
+                    != self.game_process.as_ref().map(|p| (p.pid, p.start_time));
+            if game_running && (!self.hytale_was_running || replaced) {
+                if replaced {
+                    self.record_game_session(unix_now());
+                }
+                self.game_process = game_process;
+                match self.game_process {
+                    Some(ref process) => info!(
+                        "Hytale Game detected (pid {}, {}, {})",
+                        process.pid,
+                        process
+                            .exe
+                            .as_deref()
+                            .unwrap_or(Path::new(&process.name))
+                            .display(),
+                        process.runtime
+                    ),
+                    None => info!("Hytale Game detected"),
+                }
+                events::emit(Event::ProcessDetected {
+                    process: ProcessKind::Game,
+                });
+                self.game_started_at = Some(
+                    self.game_process
+                        .as_ref()
+                        .map_or_else(unix_now, |p| p.start_time as i64),
+                );
+                self.resource_stats = ResourceStats::new();
+                self.timeline.clear();
+                metrics::global().enter_state(self.log_watcher.state().kind(), Instant::now());
+                self.update_tray_status("Hytale Game detected");
+                show_notification("Hytale RPC", "Hytale Game detected");
+            } else if !game_running && self.hytale_was_running {
+                self.handle_game_exit();
+                let idle = if launcher_running {
+                    GameState::Launcher
+                } else {
+                    GameState::Unknown
+                };
+                metrics::global().enter_state(idle.kind(), Instant::now());
+            }
+
+            // CPU usage needs two refreshes, so skip the first sample
+            let usage = self.process_detector.game_usage();
+            if let Some(ref usage) = usage.filter(|_| self.hytale_was_running) {
+                self.resource_stats.record(usage);
+            }
+            let expose_usage = self.config.lock().unwrap().resources.http;
+            metrics::global().set_game_resources(usage.as_ref().filter(|_| expose_usage));
+            self.hytale_was_running = game_running;
+
+            // Handle Launcher state changes
+            if launcher_running && !self.launcher_was_running {
+                info!("Hytale Launcher detected");
+                events::emit(Event::ProcessDetected {
+                    process: ProcessKind::Launcher,
+                });
+                if !game_running {
+                    self.update_tray_status("In Launcher");
+                    metrics::global().enter_state(GameState::Launcher.kind(), Instant::now());
+                }
+            } else if !launcher_running && self.launcher_was_running {
+                info!("Hytale Launcher closed");
+                events::emit(Event::ProcessClosed {
+                    process: ProcessKind::Launcher,
+                });
+                if !game_running {
+                    metrics::global().enter_state(GameState::Unknown.kind(), Instant::now());
+                }
+            }
+            self.launcher_was_running = launcher_running;
+
+            // Priority: Game > Launcher > None
+            if game_running || launcher_running {
+                let waiting = if self.paused {
+                    Vec::new()
+                } else {
+                    self.sinks.connect()
+                };
+                if !waiting.is_empty() {
+                    self.update_tray_status(&format!("Waiting for {}...", waiting.join(", ")));
+                }
+
+                if game_running {
+                    // Core Game Logic (Log Watcher)
+                    self.log_watcher
+                        .set_game_processes(self.process_detector.game_processes());
+                    if let Err(e) = self.log_watcher.update() {
+                        warn!("Error reading log file: {}", e);
+                    }
+                    let state = self.log_watcher.state().clone();
+                    let changed = self.publish(&state, game_running, usage);
+
+                    // Update tray status on a transition or new warnings or
+                    // errors, or on every poll when it shows resource usage
+                    let health = self.log_watcher.health().total;
+                    let health_changed = health != self.tray_health;
+                    self.tray_health = health;
+                    let config_guard = self.config.lock().unwrap();
+                    let shown_usage = usage.filter(|_| config_guard.resources.tray);
+                    if changed || health_changed || shown_usage.is_some() {
+                        let mut status = format!("{} - {}", state.details(), state.state(&config_guard));
+                        if let Some(usage) = shown_usage {
+                            status.push_str(&format!(" ({})", usage.summary()));
+                        }
+                        drop(config_guard);
+                        self.update_tray_status(&status);
+                    }
+                } else {
+                    // Launcher Logic
+                    if waiting.is_empty() {
+                        self.update_tray_status("In Launcher");
+                    }
+                    self.publish(&GameState::Launcher, game_running, usage);
+                }
+            } else {
+                // Neither running - clear presence and disconnect
+                if self.sinks.any_connected() {
+                    self.sinks.clear(&self.config.lock().unwrap());
+                    self.sinks.disconnect();
+                }
+                self.update_tray_status("Waiting for Hytale...");
+
+                #[cfg(all(feature = "dbus", target_os = "linux"))]
+                if let Some(ref mut dbus) = self.dbus {
+                    dbus.update(&GameState::Unknown, None, self.paused, &self.config.lock().unwrap());
+                }
+            }
+
+            self.write_capture(false);
+            metrics::global().observe_poll_loop(loop_start.elapsed());
+            self.process_detector
+                .wait(Duration::from_millis(POLL_INTERVAL_MS));
+        }
+
+        // Cleanup
+        info!("Shutting down...");
+        self.write_capture(true);
+        self.sinks.shutdown();
+
+        Ok(())
+    }
+}
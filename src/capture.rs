@@ -0,0 +1,157 @@
+//! Capture of log messages that no pattern matched
+//!
+//! With `--capture-unknown`, every message the log watcher couldn't match is
+//! normalized (numbers, quoted strings and IP addresses replaced with
+//! placeholders), counted, and written as a report with the most frequent
+//! messages first. After a game update this shows which new lines might mark
+//! state changes.
+
+use std::collections::HashMap;
+use std::fmt::Write as _;
+use std::fs;
+use std::path::Path;
+
+use anyhow::{Context, Result};
+use regex::Regex;
+
+use crate::log_entry::LogEntry;
+
+/// File name of the report in the data directory
+pub const REPORT_FILE: &str = "unknown-lines.txt";
+
+/// Replaces the variable parts of a message with placeholders
+struct Normalizer {
+    ip: Regex,
+    quoted: Regex,
+    number: Regex,
+}
+
+impl Normalizer {
+    fn new() -> Self {
+        Self {
+            ip: Regex::new(r"\b\d{1,3}(?:\.\d{1,3}){3}(?::\d+)?\b|\[[0-9a-fA-F:]*:[0-9a-fA-F:]*\](?::\d+)?")
+                .unwrap(),
+            quoted: Regex::new(r#""[^"]*"|'[^']*'"#).unwrap(),
+            number: Regex::new(r"\b0x[0-9a-fA-F]+\b|[-+]?\b\d+(?:\.\d+)*").unwrap(),
+        }
+    }
+
+    fn normalize(&self, message: &str) -> String {
+        let message = self.ip.replace_all(message, "<ip>");
+        let message = self.quoted.replace_all(&message, "\"<str>\"");
+        self.number.replace_all(&message, "<n>").into_owned()
+    }
+}
+
+/// Counts of unmatched messages by source and normalized message
+pub struct UnknownLines {
+    normalizer: Normalizer,
+    counts: HashMap<(String, String), u64>,
+    total: u64,
+}
+
+impl UnknownLines {
+    /// Create an empty capture
+    pub fn new() -> Self {
+        Self {
+            normalizer: Normalizer::new(),
+            counts: HashMap::new(),
+            total: 0,
+        }
+    }
+
+    /// Count an entry that no pattern matched
+    pub fn record(&mut self, entry: &LogEntry) {
+        let source = entry.source.clone().unwrap_or_default();
+        let message = self.normalizer.normalize(&entry.message);
+        *self.counts.entry((source, message)).or_default() += 1;
+        self.total += 1;
+    }
+
+    /// Number of messages recorded so far
+    pub fn total(&self) -> u64 {
+        self.total
+    }
+
+    /// Normalized messages with their source and count, most frequent first
+    pub fn sorted(&self) -> Vec<(u64, &str, &str)> {
+        let mut rows: Vec<(u64, &str, &str)> = self
+            .counts
+            .iter()
+            .map(|((source, message), count)| (*count, source.as_str(), message.as_str()))
+            .collect();
+        rows.sort_by(|a, b| b.0.cmp(&a.0).then_with(|| (a.1, a.2).cmp(&(b.1, b.2))));
+        rows
+    }
+
+    /// Render the frequency-sorted report
+    pub fn report(&self) -> String {
+        let mut out = String::new();
+        let _ = writeln!(
+            out,
+            "# Unmatched log messages: {} lines, {} distinct, most frequent first",
+            self.total,
+            self.counts.len()
+        );
+        let _ = writeln!(out, "# count\tsource\tmessage");
+        for (count, source, message) in self.sorted() {
+            let _ = writeln!(out, "{}\t{}\t{}", count, source, message);
+        }
+        out
+    }
+
+    /// Write the report to `path`
+    pub fn write_report(&self, path: &Path) -> Result<()> {
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::write(path, self.report()).with_context(|| format!("Failed to write {}", path.display()))
+    }
+}
+
+impl Default for UnknownLines {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_normalize() {
+        let normalizer = Normalizer::new();
+        assert_eq!(
+            normalizer.normalize(r#"Loaded 1532 assets in 12.5ms from "Pack A""#),
+            r#"Loaded <n> assets in <n>ms from "<str>""#
+        );
+        assert_eq!(
+            normalizer.normalize("Ping to 192.168.1.20:5520 took 31 ms"),
+            "Ping to <ip> took <n> ms"
+        );
+        assert_eq!(normalizer.normalize("Handle 0x7f3a freed"), "Handle <n> freed");
+        assert_eq!(normalizer.normalize("Stage2 ready"), "Stage2 ready");
+    }
+
+    #[test]
+    fn test_report() {
+        let mut capture = UnknownLines::new();
+        for line in [
+            "2026-01-25 11:00:00.0000|INFO|Assets|Loaded 10 textures",
+            "2026-01-25 11:00:01.0000|INFO|Assets|Loaded 25 textures",
+            "2026-01-25 11:00:02.0000|INFO|Net|Ping 31 ms",
+        ] {
+            capture.record(&LogEntry::parse(line).unwrap());
+        }
+
+        assert_eq!(capture.total(), 3);
+        assert_eq!(
+            capture.sorted(),
+            [(2, "Assets", "Loaded <n> textures"), (1, "Net", "Ping <n> ms")]
+        );
+        let report = capture.report();
+        assert!(report.starts_with("# Unmatched log messages: 3 lines, 2 distinct"));
+        assert!(report.contains("\n2\tAssets\tLoaded <n> textures\n"));
+    }
+}
@@ -1,17 +1,24 @@
 //! Configuration module with platform-specific paths and constants
 
+use std::collections::BTreeMap;
 use std::path::PathBuf;
 
 /// Discord Application Client ID for Hytale RPC
+#[cfg(feature = "discord")]
 pub const CLIENT_ID: &str = "1461306150497550376";
 
 /// Discord RPC asset names
+#[cfg(feature = "discord")]
 pub const LARGE_IMAGE: &str = "hytale_logo";
+#[cfg(feature = "discord")]
 pub const LARGE_TEXT: &str = "Hytale";
 
 /// Polling interval in milliseconds
 pub const POLL_INTERVAL_MS: u64 = 3000;
 
+/// Default port for the local HTTP status server
+pub const DEFAULT_HTTP_PORT: u16 = 47823;
+
 /// Process names to detect for Hytale Game Client
 pub const HYTALE_GAME_PROCESSES: &[&str] = &[
     "hytale",
@@ -76,7 +83,13 @@ pub fn get_log_directories() -> Vec<PathBuf> {
 pub const LOG_FILE_PATTERN: &str = "*_client.log";
 
 /// Game states
-#[derive(Debug, Clone, PartialEq, Eq)]
+///
+/// Serialized as an object tagged by `state` (the same names as
+/// [`GameState::kind`]), e.g. `{"state": "singleplayer", "world_name": "Orbis"}`.
+#[derive(
+    Debug, Clone, Default, PartialEq, Eq, serde::Serialize, serde::Deserialize, schemars::JsonSchema,
+)]
+#[serde(tag = "state", rename_all = "snake_case")]
 pub enum GameState {
     /// In the Hytale Launcher
     Launcher,
@@ -96,15 +109,10 @@ pub enum GameState {
         server_name: Option<String>,
     },
     /// Unknown/waiting state
+    #[default]
     Unknown,
 }
 
-impl Default for GameState {
-    fn default() -> Self {
-        Self::Unknown
-    }
-}
-
 impl GameState {
     /// Get Discord RPC details string
     pub fn details(&self) -> &str {
@@ -134,26 +142,12 @@ impl GameState {
             GameState::Launcher => "Ready to Play".to_string(),
             GameState::MainMenu => "Idle".to_string(),
             GameState::Loading { world_name, sub_stage, .. } => {
-                 if let Some(_) = sub_stage {
-                     // If we have a sub_stage in details ("Loading..."), put world name here
-                     if config.show_world_name {
-                         world_name
-                            .as_ref()
-                            .map(|n| n.clone())
-                            .unwrap_or_else(|| "Please wait...".to_string())
-                     } else {
-                         "Please wait...".to_string()
-                     }
-                 } else {
-                     if config.show_world_name {
-                         world_name
-                            .as_ref()
-                            .map(|n| n.clone())
-                            .unwrap_or_else(|| "...".to_string())
-                     } else {
-                         "...".to_string()
-                     }
-                 }
+                // With a sub_stage in details ("Loading..."), put the world name here
+                let fallback = if sub_stage.is_some() { "Please wait..." } else { "..." };
+                match world_name {
+                    Some(name) if config.show_world_name => name.clone(),
+                    _ => fallback.to_string(),
+                }
             },
             GameState::Singleplayer { world_name } => {
                 if config.show_world_name {
@@ -186,6 +180,79 @@ impl GameState {
     pub fn is_in_game(&self) -> bool {
         matches!(self, GameState::Singleplayer { .. } | GameState::Multiplayer { .. })
     }
+
+    /// Short machine-readable name of the state
+    pub fn kind(&self) -> &'static str {
+        match self {
+            GameState::Launcher => "launcher",
+            GameState::MainMenu => "main_menu",
+            GameState::Loading { .. } => "loading",
+            GameState::Singleplayer { .. } => "singleplayer",
+            GameState::Multiplayer { .. } => "multiplayer",
+            GameState::Unknown => "unknown",
+        }
+    }
+
+    /// JSON schema of the serialized form
+    pub fn json_schema() -> serde_json::Value {
+        serde_json::to_value(schemars::schema_for!(GameState)).unwrap_or_default()
+    }
+
+    /// Copy with the world and server cleared where the config hides them,
+    /// for outputs that serialize the whole state
+    pub fn redacted(&self, config: &AppConfig) -> GameState {
+        let mut state = self.clone();
+        match &mut state {
+            GameState::Loading { world_name, .. } if !config.show_world_name => *world_name = None,
+            GameState::Singleplayer { world_name } if !config.show_world_name => world_name.clear(),
+            GameState::Multiplayer {
+                server_address,
+                server_name,
+            } if !config.show_server_ip => {
+                *server_address = None;
+                *server_name = None;
+            }
+            _ => {}
+        }
+        state
+    }
+
+    /// World name, if known
+    pub fn world_name(&self) -> Option<&str> {
+        match self {
+            GameState::Loading { world_name, .. } => world_name.as_deref(),
+            GameState::Singleplayer { world_name } => Some(world_name),
+            _ => None,
+        }
+    }
+
+    /// Server address, if playing multiplayer
+    pub fn server_address(&self) -> Option<&str> {
+        match self {
+            GameState::Multiplayer { server_address, .. } => server_address.as_deref(),
+            _ => None,
+        }
+    }
+
+    /// Server name, if playing multiplayer and the server announced one
+    pub fn server_name(&self) -> Option<&str> {
+        match self {
+            GameState::Multiplayer { server_name, .. } => server_name.as_deref(),
+            _ => None,
+        }
+    }
+
+    /// World name, if known and the config allows showing it
+    pub fn shown_world(&self, config: &AppConfig) -> Option<&str> {
+        self.world_name().filter(|_| config.show_world_name)
+    }
+
+    /// Server name (or address), if known and the config allows showing it
+    pub fn shown_server(&self, config: &AppConfig) -> Option<&str> {
+        self.server_name()
+            .or_else(|| self.server_address())
+            .filter(|_| config.show_server_ip)
+    }
 }
 
 /// Application configuration
@@ -193,6 +260,20 @@ impl GameState {
 pub struct AppConfig {
     pub show_world_name: bool,
     pub show_server_ip: bool,
+    #[serde(default)]
+    pub discord: DiscordConfig,
+    #[serde(default)]
+    pub http: HttpConfig,
+    #[serde(default)]
+    pub file_output: FileOutputConfig,
+    #[serde(default)]
+    pub webhook: WebhookConfig,
+    #[serde(default)]
+    pub mqtt: MqttConfig,
+    #[serde(default)]
+    pub dbus: DbusConfig,
+    #[serde(default)]
+    pub resources: ResourcesConfig,
 }
 
 impl Default for AppConfig {
@@ -200,6 +281,206 @@ impl Default for AppConfig {
         Self {
             show_world_name: true,
             show_server_ip: true,
+            discord: DiscordConfig::default(),
+            http: HttpConfig::default(),
+            file_output: FileOutputConfig::default(),
+            webhook: WebhookConfig::default(),
+            mqtt: MqttConfig::default(),
+            dbus: DbusConfig::default(),
+            resources: ResourcesConfig::default(),
+        }
+    }
+}
+
+/// Discord Rich Presence output configuration
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+#[serde(default)]
+pub struct DiscordConfig {
+    pub enabled: bool,
+    /// Least seconds a state is shown before Discord switches to a state
+    /// of another kind, by [`GameState::kind`]
+    pub min_display_secs: BTreeMap<String, u64>,
+}
+
+impl Default for DiscordConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            min_display_secs: BTreeMap::new(),
+        }
+    }
+}
+
+/// Local HTTP status server configuration
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+#[serde(default)]
+pub struct HttpConfig {
+    /// Serve `/status` and `/events` on 127.0.0.1
+    pub enabled: bool,
+    pub port: u16,
+    /// Require `Authorization: Bearer <token>` when set
+    pub token: Option<String>,
+    /// Also serve Prometheus metrics on `/metrics`
+    pub metrics: bool,
+}
+
+impl Default for HttpConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            port: DEFAULT_HTTP_PORT,
+            token: None,
+            metrics: false,
+        }
+    }
+}
+
+/// Text/JSON file output for streaming software (e.g. OBS text sources)
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+#[serde(default)]
+pub struct FileOutputConfig {
+    pub enabled: bool,
+    /// Plain text file rendered from `text_template`
+    pub text_path: Option<PathBuf>,
+    /// JSON file with the rendered strings and the state fields
+    pub json_path: Option<PathBuf>,
+    /// Template for the text file; supports `{details}`, `{state}`, `{world}` and `{server}`
+    pub text_template: String,
+    /// Text written when the game is not running
+    pub idle_template: String,
+}
+
+impl Default for FileOutputConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            text_path: Some(get_data_dir().join("now-playing.txt")),
+            json_path: Some(get_data_dir().join("now-playing.json")),
+            text_template: "{details} - {state}".to_string(),
+            idle_template: String::new(),
+        }
+    }
+}
+
+/// Events that can trigger a webhook
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum WebhookEvent {
+    /// The game process was detected
+    GameStart,
+    /// Joined a multiplayer server
+    ServerJoin,
+    /// Left a multiplayer server
+    ServerLeave,
+    /// The game process closed
+    SessionEnd,
+}
+
+/// Payload format for webhooks
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum WebhookFormat {
+    /// Plain JSON event object
+    Json,
+    /// Discord webhook message (`content` field)
+    Discord,
+}
+
+/// Outgoing webhook configuration
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+#[serde(default)]
+pub struct WebhookConfig {
+    pub enabled: bool,
+    pub url: Option<String>,
+    pub format: WebhookFormat,
+    pub events: Vec<WebhookEvent>,
+    /// Name used in Discord-formatted messages, e.g. "Steve joined ..."
+    pub display_name: Option<String>,
+}
+
+impl Default for WebhookConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            url: None,
+            format: WebhookFormat::Json,
+            events: vec![
+                WebhookEvent::GameStart,
+                WebhookEvent::ServerJoin,
+                WebhookEvent::ServerLeave,
+                WebhookEvent::SessionEnd,
+            ],
+            display_name: None,
+        }
+    }
+}
+
+/// MQTT publisher configuration
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+#[serde(default)]
+pub struct MqttConfig {
+    pub enabled: bool,
+    /// Broker URL, e.g. `mqtt://homeassistant.local:1883` or `mqtts://broker:8883`
+    pub broker_url: String,
+    pub username: Option<String>,
+    pub password: Option<String>,
+    pub client_id: String,
+    /// Prefix for all published topics
+    pub topic_prefix: String,
+    /// Use TLS even if the URL scheme is `mqtt://`
+    pub tls: bool,
+    /// Publish Home Assistant MQTT discovery configs
+    pub ha_discovery: bool,
+    pub discovery_prefix: String,
+}
+
+impl Default for MqttConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            broker_url: "mqtt://localhost:1883".to_string(),
+            username: None,
+            password: None,
+            client_id: "hytale-rpc".to_string(),
+            topic_prefix: "hytale-rpc".to_string(),
+            tls: false,
+            ha_discovery: true,
+            discovery_prefix: "homeassistant".to_string(),
+        }
+    }
+}
+
+/// D-Bus service configuration (Linux only)
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+#[serde(default)]
+pub struct DbusConfig {
+    pub enabled: bool,
+}
+
+impl Default for DbusConfig {
+    fn default() -> Self {
+        Self { enabled: true }
+    }
+}
+
+/// Game client CPU and memory reporting
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+#[serde(default)]
+pub struct ResourcesConfig {
+    /// Record min/avg/max per game session in the session history
+    pub history: bool,
+    /// Show current usage in the tray tooltip
+    pub tray: bool,
+    /// Include usage in `/status` and `/metrics`
+    pub http: bool,
+}
+
+impl Default for ResourcesConfig {
+    fn default() -> Self {
+        Self {
+            history: true,
+            tray: false,
+            http: false,
         }
     }
 }
@@ -230,9 +511,148 @@ impl AppConfig {
     }
 }
 
-fn get_config_path() -> PathBuf {
+/// Directory for files written by the application (outputs, queues, history)
+pub fn get_data_dir() -> PathBuf {
+    let mut path = dirs::data_dir().unwrap_or_else(|| PathBuf::from("."));
+    path.push("hytale-rpc");
+    path
+}
+
+/// Location of the config file
+pub fn get_config_path() -> PathBuf {
     let mut path = dirs::config_dir().unwrap_or_else(|| PathBuf::from("."));
     path.push("hytale-rpc");
     path.push("config.json");
     path
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn all_states() -> Vec<GameState> {
+        vec![
+            GameState::Launcher,
+            GameState::MainMenu,
+            GameState::Loading {
+                world_name: Some("Orbis".to_string()),
+                is_multiplayer: false,
+                sub_stage: Some("Loading: Booting Server".to_string()),
+            },
+            GameState::Loading {
+                world_name: None,
+                is_multiplayer: true,
+                sub_stage: None,
+            },
+            GameState::Singleplayer {
+                world_name: "Orbis".to_string(),
+            },
+            GameState::Multiplayer {
+                server_address: Some("play.example.com:5520".to_string()),
+                server_name: Some("Example SMP".to_string()),
+            },
+            GameState::Unknown,
+        ]
+    }
+
+    #[test]
+    fn test_game_state_round_trip() {
+        for state in all_states() {
+            let json = serde_json::to_string(&state).unwrap();
+            let parsed: GameState = serde_json::from_str(&json).unwrap();
+            assert_eq!(parsed, state, "{}", json);
+
+            let value = serde_json::to_value(&state).unwrap();
+            assert_eq!(value["state"], state.kind());
+        }
+    }
+
+    #[test]
+    fn test_game_state_representation() {
+        let state = GameState::Multiplayer {
+            server_address: Some("play.example.com:5520".to_string()),
+            server_name: None,
+        };
+        assert_eq!(
+            serde_json::to_string(&state).unwrap(),
+            r#"{"state":"multiplayer","server_address":"play.example.com:5520","server_name":null}"#
+        );
+        assert_eq!(
+            serde_json::to_string(&GameState::MainMenu).unwrap(),
+            r#"{"state":"main_menu"}"#
+        );
+    }
+
+    #[test]
+    fn test_redacted() {
+        let config = AppConfig {
+            show_world_name: false,
+            show_server_ip: false,
+            ..Default::default()
+        };
+        let redacted: Vec<GameState> = all_states().iter().map(|s| s.redacted(&config)).collect();
+        assert_eq!(
+            redacted[2],
+            GameState::Loading {
+                world_name: None,
+                is_multiplayer: false,
+                sub_stage: Some("Loading: Booting Server".to_string()),
+            }
+        );
+        assert_eq!(
+            redacted[4],
+            GameState::Singleplayer {
+                world_name: String::new(),
+            }
+        );
+        assert_eq!(
+            redacted[5],
+            GameState::Multiplayer {
+                server_address: None,
+                server_name: None,
+            }
+        );
+
+        let state = &all_states()[5];
+        assert_eq!(state.redacted(&AppConfig::default()), *state);
+    }
+
+    #[test]
+    fn test_loading_state_text() {
+        let hidden = AppConfig {
+            show_world_name: false,
+            ..Default::default()
+        };
+        let mut state = GameState::Loading {
+            world_name: Some("Orbis".to_string()),
+            is_multiplayer: false,
+            sub_stage: Some("Loading...".to_string()),
+        };
+        assert_eq!(state.state(&AppConfig::default()), "Orbis");
+        assert_eq!(state.state(&hidden), "Please wait...");
+
+        if let GameState::Loading { ref mut sub_stage, .. } = state {
+            *sub_stage = None;
+        }
+        assert_eq!(state.state(&hidden), "...");
+    }
+
+    /// Regenerate the checked-in schema with `BLESS=1 cargo test`
+    #[test]
+    fn test_game_state_schema_is_up_to_date() {
+        let path = std::path::Path::new(env!("CARGO_MANIFEST_DIR")).join("docs/game-state.schema.json");
+        let schema = serde_json::to_string_pretty(&GameState::json_schema()).unwrap() + "\n";
+
+        if std::env::var_os("BLESS").is_some() {
+            std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+            std::fs::write(&path, &schema).unwrap();
+        }
+
+        let checked_in = std::fs::read_to_string(&path).unwrap_or_default();
+        assert!(
+            checked_in == schema,
+            "{} is out of date, run `BLESS=1 cargo test` to regenerate it",
+            path.display()
+        );
+    }
+}
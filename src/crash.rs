@@ -0,0 +1,223 @@
+//! Crash detection when the game client exits
+//!
+//! The game isn't a child of this app, so its exit code can't be read.
+//! Instead, the end of its log (fatal errors, unhandled exceptions, a missing
+//! shutdown message) and new crash dumps next to the logs tell crashes apart
+//! from clean exits. Each crash is saved as a JSON bundle in `crashes/` in
+//! the data directory.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+use std::time::UNIX_EPOCH;
+
+use anyhow::{Context, Result};
+use regex::Regex;
+use serde::Serialize;
+
+use crate::log_entry::{LogEntry, LogLevel};
+use crate::process::DetectedProcess;
+use crate::state_machine::Transition;
+
+/// Directory in the data directory that crash bundles are written to
+pub const CRASH_DIR: &str = "crashes";
+
+/// How many of the last log lines are searched for errors
+const TAIL_LINES: usize = 50;
+
+/// Extensions of crash dump files
+const DUMP_EXTENSIONS: &[&str] = &["dmp", "mdmp"];
+
+/// What the end of a game session looked like
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ExitAnalysis {
+    /// Whether there is evidence of a crash
+    pub crashed: bool,
+    /// The error that most likely caused the crash
+    pub headline: Option<String>,
+    /// Everything that looked wrong, for the crash bundle
+    pub reasons: Vec<String>,
+    pub dumps: Vec<PathBuf>,
+}
+
+fn exception_pattern() -> &'static Regex {
+    static PATTERN: OnceLock<Regex> = OnceLock::new();
+    PATTERN.get_or_init(|| Regex::new(r"\b[A-Z]\w*(?:\.\w+)*Exception\b").unwrap())
+}
+
+fn unhandled_pattern() -> &'static Regex {
+    static PATTERN: OnceLock<Regex> = OnceLock::new();
+    PATTERN.get_or_init(|| Regex::new(r"(?i)unhandled exception").unwrap())
+}
+
+/// Decide whether the game crashed from the last log lines, whether it
+/// logged its shutdown and the crash dumps written during the session.
+///
+/// Fatal errors and unhandled exceptions always count as a crash. Other
+/// exceptions logged as errors only do when the game didn't log an orderly
+/// shutdown, since a clean exit can still log a handled one.
+pub fn analyze(lines: &[String], shutdown_seen: bool, dumps: Vec<PathBuf>) -> ExitAnalysis {
+    let mut analysis = ExitAnalysis::default();
+
+    let tail = &lines[lines.len().saturating_sub(TAIL_LINES)..];
+    for entry in tail.iter().filter_map(|line| LogEntry::parse(line)) {
+        let is_crash = match entry.level {
+            Some(LogLevel::Fatal) => true,
+            Some(LogLevel::Error) => {
+                unhandled_pattern().is_match(&entry.message)
+                    || (!shutdown_seen && exception_pattern().is_match(&entry.message))
+            }
+            _ => false,
+        };
+        if is_crash {
+            analysis.crashed = true;
+            analysis.headline.get_or_insert_with(|| entry.message.clone());
+            analysis.reasons.push(format!("Error in log: {}", entry.message));
+        }
+    }
+
+    if !dumps.is_empty() {
+        analysis.crashed = true;
+        for dump in &dumps {
+            analysis.reasons.push(format!("Crash dump: {}", dump.display()));
+        }
+    }
+    analysis.dumps = dumps;
+
+    if !shutdown_seen && !lines.is_empty() {
+        analysis.reasons.push("No shutdown message in the log".to_string());
+    }
+
+    analysis
+}
+
+/// Crash dumps in `dirs` modified at or after `since` (Unix seconds)
+pub fn find_dumps(dirs: &[&Path], since: i64) -> Vec<PathBuf> {
+    let mut dumps = Vec::new();
+    for dir in dirs {
+        let Ok(entries) = fs::read_dir(dir) else {
+            continue;
+        };
+        for entry in entries.flatten() {
+            let path = entry.path();
+            let name = path
+                .file_name()
+                .map(|n| n.to_string_lossy().to_lowercase())
+                .unwrap_or_default();
+            let is_dump = name.contains("crash")
+                || path
+                    .extension()
+                    .is_some_and(|ext| DUMP_EXTENSIONS.contains(&ext.to_string_lossy().to_lowercase().as_str()));
+            let modified = entry
+                .metadata()
+                .and_then(|m| m.modified())
+                .ok()
+                .and_then(|t| t.duration_since(UNIX_EPOCH).ok())
+                .map(|d| d.as_secs() as i64);
+            if is_dump && path.is_file() && modified.is_some_and(|m| m >= since) {
+                dumps.push(path);
+            }
+        }
+    }
+    dumps.sort();
+    dumps
+}
+
+/// Everything saved about a crash
+#[derive(Debug, Clone, Serialize)]
+pub struct CrashBundle {
+    /// Unix timestamp (seconds) at which the crash was noticed
+    pub created_at: i64,
+    pub headline: Option<String>,
+    pub reasons: Vec<String>,
+    pub dumps: Vec<PathBuf>,
+    pub process: Option<DetectedProcess>,
+    pub log_path: Option<PathBuf>,
+    /// State changes during the session, oldest first
+    pub timeline: Vec<Transition>,
+    /// The last lines of the log
+    pub last_lines: Vec<String>,
+}
+
+impl CrashBundle {
+    /// Write the bundle to `dir` and return its path
+    pub fn save(&self, dir: &Path) -> Result<PathBuf> {
+        fs::create_dir_all(dir)?;
+        let path = dir.join(format!("crash-{}.json", self.created_at));
+        let json = serde_json::to_string_pretty(self)?;
+        fs::write(&path, json).with_context(|| format!("Failed to write {}", path.display()))?;
+        Ok(path)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn lines(lines: &[&str]) -> Vec<String> {
+        lines.iter().map(|l| l.to_string()).collect()
+    }
+
+    #[test]
+    fn test_clean_exit() {
+        let log = lines(&[
+            "2026-01-25 11:20:00.0000|WARN|Net|Retrying after IOException: timeout",
+            "2026-01-25 11:21:00.0000|INFO|App|Shutting down",
+        ]);
+        let analysis = analyze(&log, true, Vec::new());
+        assert!(!analysis.crashed);
+        assert!(analysis.reasons.is_empty());
+
+        // A missing shutdown message alone is noted but isn't a crash
+        let analysis = analyze(&log[..1], false, Vec::new());
+        assert!(!analysis.crashed);
+        assert_eq!(analysis.reasons.len(), 1);
+    }
+
+    #[test]
+    fn test_clean_exit_with_logged_exception() {
+        let log = lines(&[
+            "2026-01-25 11:20:00.0000|ERROR|Net|Disconnected: System.IO.IOException: Connection reset",
+            "System.IO.IOException: Connection reset",
+            "2026-01-25 11:21:00.0000|INFO|App|Shutting down",
+        ]);
+        let analysis = analyze(&log, true, Vec::new());
+        assert!(!analysis.crashed);
+        assert!(analysis.reasons.is_empty());
+
+        // Without the shutdown message the same error points to a crash
+        let analysis = analyze(&log[..2], false, Vec::new());
+        assert!(analysis.crashed);
+        assert_eq!(
+            analysis.headline.as_deref(),
+            Some("Disconnected: System.IO.IOException: Connection reset")
+        );
+    }
+
+    #[test]
+    fn test_crash_from_log_and_dump() {
+        let log = lines(&[
+            "2026-01-25 11:20:00.0000|INFO|App|Changing from Stage GameLoading to InGame",
+            "2026-01-25 11:21:00.0000|ERROR|App|Unhandled exception: System.NullReferenceException: Object reference not set",
+            "   at HytaleClient.Game.Update()",
+        ]);
+        let analysis = analyze(&log, false, Vec::new());
+        assert!(analysis.crashed);
+        assert_eq!(
+            analysis.headline.as_deref(),
+            Some("Unhandled exception: System.NullReferenceException: Object reference not set")
+        );
+        assert_eq!(analysis.reasons.len(), 2);
+
+        let dir = tempfile::tempdir().unwrap();
+        fs::write(dir.path().join("HytaleClient.dmp"), "").unwrap();
+        fs::write(dir.path().join("2026-01-25_client.log"), "").unwrap();
+        let dumps = find_dumps(&[dir.path()], 0);
+        assert_eq!(dumps, [dir.path().join("HytaleClient.dmp")]);
+        assert!(find_dumps(&[dir.path()], i64::MAX).is_empty());
+
+        let analysis = analyze(&[], true, dumps);
+        assert!(analysis.crashed);
+        assert_eq!(analysis.headline, None);
+    }
+}
@@ -0,0 +1,311 @@
+//! D-Bus service exposing state and controls on Linux
+//!
+//! Registers `org.hytale.Rpc` on the session bus with an object at
+//! `/org/hytale/Rpc`. Desktop widgets can read the `State`, `Server`,
+//! `World`, `SessionStart` and `Paused` properties (with PropertiesChanged
+//! signals) and call `Pause`, `Resume` and `SetOption`. `Server` and
+//! `World` follow the privacy options and are blank while paused.
+
+use std::sync::mpsc::{self, Receiver, Sender, TryRecvError};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::Duration;
+
+use anyhow::{Context, Result};
+use dbus::arg::{PropMap, RefArg, Variant};
+use dbus::blocking::stdintf::org_freedesktop_dbus::PropertiesPropertiesChanged;
+use dbus::blocking::Connection;
+use dbus::channel::{MatchingReceiver, Sender as _};
+use dbus::message::{MatchRule, SignalArgs};
+use dbus_crossroads::{Crossroads, MethodErr};
+use log::{debug, info, warn};
+
+use crate::config::{AppConfig, GameState};
+
+/// Well-known bus name and interface name
+pub const BUS_NAME: &str = "org.hytale.Rpc";
+
+/// Object path of the service object
+pub const OBJECT_PATH: &str = "/org/hytale/Rpc";
+
+/// Options that can be changed with `SetOption`
+pub const OPTIONS: &[&str] = &["show_world_name", "show_server_ip"];
+
+/// How long a single D-Bus dispatch waits before checking for state updates
+const PROCESS_TIMEOUT: Duration = Duration::from_millis(250);
+
+/// Commands received over D-Bus
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ControlCommand {
+    Pause,
+    Resume,
+    SetOption { name: String, value: bool },
+}
+
+/// Property values as exposed on the bus
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+struct Properties {
+    state: String,
+    server: String,
+    world: String,
+    session_start: i64,
+    paused: bool,
+}
+
+impl Properties {
+    fn new(state: &GameState, session_start: Option<i64>, paused: bool, config: &AppConfig) -> Self {
+        let shown = |value: Option<&str>| value.filter(|_| !paused).unwrap_or_default().to_string();
+        Self {
+            state: state.kind().to_string(),
+            server: shown(state.shown_server(config)),
+            world: shown(state.shown_world(config)),
+            session_start: session_start.unwrap_or(0),
+            paused,
+        }
+    }
+}
+
+/// Properties that differ between two snapshots, as sent in PropertiesChanged
+fn changed_properties(old: &Properties, new: &Properties) -> PropMap {
+    let mut changed = PropMap::new();
+    let mut put = |name: &str, value: Box<dyn RefArg>| {
+        changed.insert(name.to_string(), Variant(value));
+    };
+
+    if old.state != new.state {
+        put("State", Box::new(new.state.clone()));
+    }
+    if old.server != new.server {
+        put("Server", Box::new(new.server.clone()));
+    }
+    if old.world != new.world {
+        put("World", Box::new(new.world.clone()));
+    }
+    if old.session_start != new.session_start {
+        put("SessionStart", Box::new(new.session_start));
+    }
+    if old.paused != new.paused {
+        put("Paused", Box::new(new.paused));
+    }
+    changed
+}
+
+/// Handle to the D-Bus service thread
+pub struct DbusService {
+    updates: Sender<Properties>,
+    commands: Receiver<ControlCommand>,
+    last_sent: Option<Properties>,
+}
+
+impl DbusService {
+    /// Connect to the session bus and start serving in a background thread
+    pub fn start() -> Result<Self> {
+        let (update_tx, update_rx) = mpsc::channel();
+        let (command_tx, command_rx) = mpsc::channel();
+        let (ready_tx, ready_rx) = mpsc::channel();
+
+        thread::spawn(move || {
+            let conn = match register(command_tx) {
+                Ok((conn, properties)) => {
+                    let _ = ready_tx.send(Ok(()));
+                    (conn, properties)
+                }
+                Err(e) => {
+                    let _ = ready_tx.send(Err(e));
+                    return;
+                }
+            };
+            if let Err(e) = serve(conn.0, conn.1, update_rx) {
+                warn!("D-Bus service stopped: {}", e);
+            }
+        });
+
+        ready_rx.recv().context("D-Bus service thread exited")??;
+        info!("D-Bus service registered as {}", BUS_NAME);
+
+        Ok(Self {
+            updates: update_tx,
+            commands: command_rx,
+            last_sent: None,
+        })
+    }
+
+    /// Publish the state the app is currently tracking
+    pub fn update(
+        &mut self,
+        state: &GameState,
+        session_start: Option<i64>,
+        paused: bool,
+        config: &AppConfig,
+    ) {
+        let properties = Properties::new(state, session_start, paused, config);
+        if self.last_sent.as_ref() == Some(&properties) {
+            return;
+        }
+        if self.updates.send(properties.clone()).is_ok() {
+            self.last_sent = Some(properties);
+        }
+    }
+
+    /// Get the next command received over D-Bus, if any
+    pub fn poll_command(&self) -> Option<ControlCommand> {
+        self.commands.try_recv().ok()
+    }
+}
+
+/// Claim the bus name and register the service object
+fn register(commands: Sender<ControlCommand>) -> Result<(Connection, Arc<Mutex<Properties>>)> {
+    let conn = Connection::new_session().context("Failed to connect to the session bus")?;
+    conn.request_name(BUS_NAME, false, true, true)
+        .with_context(|| format!("Failed to claim bus name {}", BUS_NAME))?;
+
+    let properties = Arc::new(Mutex::new(Properties {
+        state: GameState::Unknown.kind().to_string(),
+        ..Default::default()
+    }));
+
+    let mut cr = Crossroads::new();
+    let iface = cr.register(BUS_NAME, move |b| {
+        b.property("State")
+            .get(|_, props: &mut Arc<Mutex<Properties>>| Ok(props.lock().unwrap().state.clone()));
+        b.property("Server")
+            .get(|_, props: &mut Arc<Mutex<Properties>>| Ok(props.lock().unwrap().server.clone()));
+        b.property("World")
+            .get(|_, props: &mut Arc<Mutex<Properties>>| Ok(props.lock().unwrap().world.clone()));
+        b.property("SessionStart")
+            .get(|_, props: &mut Arc<Mutex<Properties>>| Ok(props.lock().unwrap().session_start));
+        b.property("Paused")
+            .get(|_, props: &mut Arc<Mutex<Properties>>| Ok(props.lock().unwrap().paused));
+
+        let tx = commands.clone();
+        b.method("Pause", (), (), move |_, _, ()| {
+            let _ = tx.send(ControlCommand::Pause);
+            Ok(())
+        });
+        let tx = commands.clone();
+        b.method("Resume", (), (), move |_, _, ()| {
+            let _ = tx.send(ControlCommand::Resume);
+            Ok(())
+        });
+        let tx = commands.clone();
+        b.method(
+            "SetOption",
+            ("name", "value"),
+            (),
+            move |_, _, (name, value): (String, bool)| {
+                if !OPTIONS.contains(&name.as_str()) {
+                    return Err(MethodErr::invalid_arg(&name));
+                }
+                let _ = tx.send(ControlCommand::SetOption { name, value });
+                Ok(())
+            },
+        );
+    });
+    cr.insert(OBJECT_PATH, &[iface], properties.clone());
+
+    conn.start_receive(
+        MatchRule::new_method_call(),
+        Box::new(move |msg, conn| {
+            let _ = cr.handle_message(msg, conn);
+            true
+        }),
+    );
+
+    Ok((conn, properties))
+}
+
+/// Dispatch D-Bus messages and emit PropertiesChanged for state updates
+fn serve(
+    conn: Connection,
+    properties: Arc<Mutex<Properties>>,
+    updates: Receiver<Properties>,
+) -> Result<()> {
+    loop {
+        conn.process(PROCESS_TIMEOUT)?;
+
+        loop {
+            let new = match updates.try_recv() {
+                Ok(new) => new,
+                Err(TryRecvError::Empty) => break,
+                Err(TryRecvError::Disconnected) => return Ok(()),
+            };
+
+            let changed = {
+                let mut current = properties.lock().unwrap();
+                let changed = changed_properties(&current, &new);
+                *current = new;
+                changed
+            };
+            if changed.is_empty() {
+                continue;
+            }
+
+            debug!("D-Bus properties changed: {:?}", changed.keys());
+            let signal = PropertiesPropertiesChanged {
+                interface_name: BUS_NAME.to_string(),
+                changed_properties: changed,
+                invalidated_properties: Vec::new(),
+            };
+            let _ = conn.send(signal.to_emit_message(&OBJECT_PATH.into()));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_changed_properties() {
+        let config = AppConfig::default();
+        let menu = Properties::new(&GameState::MainMenu, None, false, &config);
+        let playing = Properties::new(
+            &GameState::Multiplayer {
+                server_address: Some("smp.example.com:5520".to_string()),
+                server_name: None,
+            },
+            Some(1_700_000_000),
+            false,
+            &config,
+        );
+
+        let changed = changed_properties(&menu, &playing);
+        let mut names: Vec<&str> = changed.keys().map(|k| k.as_str()).collect();
+        names.sort();
+        assert_eq!(names, ["Server", "SessionStart", "State"]);
+        assert_eq!(changed["Server"].0.as_str(), Some("smp.example.com:5520"));
+
+        let paused = Properties {
+            paused: true,
+            ..playing.clone()
+        };
+        assert_eq!(changed_properties(&playing, &paused).len(), 1);
+        assert!(changed_properties(&paused, &paused).is_empty());
+    }
+
+    #[test]
+    fn test_properties_hidden() {
+        let state = GameState::Singleplayer {
+            world_name: "Orbis".to_string(),
+        };
+        let server = GameState::Multiplayer {
+            server_address: Some("smp.example.com:5520".to_string()),
+            server_name: Some("Example SMP".to_string()),
+        };
+        let mut config = AppConfig::default();
+        assert_eq!(Properties::new(&state, None, false, &config).world, "Orbis");
+        assert_eq!(Properties::new(&server, None, false, &config).server, "Example SMP");
+
+        config.show_world_name = false;
+        config.show_server_ip = false;
+        assert_eq!(Properties::new(&state, None, false, &config).world, "");
+        assert_eq!(Properties::new(&server, None, false, &config).server, "");
+
+        // Paused hides them whatever the config says
+        let config = AppConfig::default();
+        let paused = Properties::new(&server, Some(1_700_000_000), true, &config);
+        assert_eq!(paused.server, "");
+        assert_eq!(paused.state, "multiplayer");
+        assert_eq!(Properties::new(&state, None, true, &config).world, "");
+    }
+}
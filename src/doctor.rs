@@ -0,0 +1,94 @@
+//! Diagnostics for the `doctor` command
+//!
+//! Reports where the app looks for logs, which game processes it sees and
+//! which log file it would watch (and why), to help with setup problems.
+
+use std::fmt::Write;
+use std::fs;
+
+use chrono::{DateTime, Local};
+
+use crate::config::{get_config_path, get_log_directories};
+use crate::log_watcher::{find_log_files, select_log_file};
+use crate::process::ProcessDetector;
+
+/// Build the diagnostics report
+pub fn report() -> String {
+    let mut out = String::new();
+    let format_time = |secs: u64| {
+        DateTime::from_timestamp(secs as i64, 0)
+            .map(|t| t.with_timezone(&Local).format("%Y-%m-%d %H:%M:%S").to_string())
+            .unwrap_or_else(|| secs.to_string())
+    };
+
+    let _ = writeln!(out, "hytale-rpc v{}", env!("CARGO_PKG_VERSION"));
+    let _ = writeln!(out, "Config: {}", get_config_path().display());
+
+    let _ = writeln!(out, "\nLog directories:");
+    for dir in get_log_directories() {
+        let status = if dir.is_dir() { "found" } else { "missing" };
+        let _ = writeln!(out, "  [{}] {}", status, dir.display());
+    }
+
+    let log_files = find_log_files();
+    let _ = writeln!(out, "\nLog files:");
+    if log_files.is_empty() {
+        let _ = writeln!(out, "  (none)");
+    }
+    for path in &log_files {
+        let Ok(metadata) = fs::metadata(path) else {
+            continue;
+        };
+        let modified = metadata
+            .modified()
+            .ok()
+            .and_then(|t| t.duration_since(std::time::UNIX_EPOCH).ok())
+            .map(|d| format_time(d.as_secs()))
+            .unwrap_or_default();
+        let _ = writeln!(
+            out,
+            "  {} ({} bytes, modified {})",
+            path.display(),
+            metadata.len(),
+            modified
+        );
+    }
+
+    let detector = ProcessDetector::new();
+    let processes = detector.game_processes();
+    let _ = writeln!(out, "\nGame processes:");
+    if processes.is_empty() {
+        let _ = writeln!(out, "  (none)");
+    }
+    for process in &processes {
+        let _ = writeln!(
+            out,
+            "  pid {} ({}, started {})",
+            process.pid,
+            process.runtime,
+            format_time(process.start_time)
+        );
+        if let Some(ref exe) = process.exe {
+            let _ = writeln!(out, "    Executable: {}", exe.display());
+        }
+        if let Some(parent) = process.parent {
+            let _ = writeln!(out, "    Parent: pid {}", parent);
+        }
+        if !process.cmdline.is_empty() {
+            let _ = writeln!(out, "    Command line: {}", process.cmdline.join(" "));
+        }
+    }
+
+    let _ = writeln!(out, "\nSelected log:");
+    match select_log_file(&log_files, &processes) {
+        Some(selection) => {
+            let _ = writeln!(out, "  {}", selection.path.display());
+            let _ = writeln!(out, "  Reason: {}", selection.reason);
+        }
+        None => {
+            let _ = writeln!(out, "  (none)");
+        }
+    }
+
+    out
+}
@@ -0,0 +1,126 @@
+//! JSON-lines event stream on stdout
+//!
+//! With `--events json`, every notable event is written to stdout as one
+//! JSON object per line so the app can be piped into other tools. Each
+//! object carries the schema `version`, a Unix `timestamp` (seconds) and the
+//! `event` name; the remaining fields depend on the event. Human-readable
+//! logs go to stderr.
+
+use std::io::Write;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use serde::Serialize;
+
+use crate::config::GameState;
+use crate::log_watcher::unix_now;
+use crate::state_machine::{Cause, Transition};
+
+/// Version of the event schema, bumped on incompatible changes
+pub const EVENT_SCHEMA_VERSION: u32 = 1;
+
+static ENABLED: AtomicBool = AtomicBool::new(false);
+
+/// Which process an event refers to
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ProcessKind {
+    Game,
+    Launcher,
+}
+
+/// An event written to the stream
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "event", rename_all = "snake_case")]
+pub enum Event {
+    ProcessDetected { process: ProcessKind },
+    ProcessClosed { process: ProcessKind },
+    GameCrashed { headline: Option<String>, bundle: Option<PathBuf> },
+    LogFileSwitched { path: PathBuf },
+    StateTransition { from: GameState, to: GameState, cause: Cause, at: i64 },
+    // Only sent by the Discord output
+    #[cfg_attr(not(feature = "discord"), allow(dead_code))]
+    DiscordConnected,
+    #[cfg_attr(not(feature = "discord"), allow(dead_code))]
+    DiscordDisconnected { error: Option<String> },
+    ConfigChanged { option: String, value: bool },
+}
+
+impl From<&Transition> for Event {
+    fn from(transition: &Transition) -> Self {
+        Event::StateTransition {
+            from: transition.from.clone(),
+            to: transition.to.clone(),
+            cause: transition.cause,
+            at: transition.at,
+        }
+    }
+}
+
+#[derive(Serialize)]
+struct Envelope<'a> {
+    version: u32,
+    timestamp: i64,
+    #[serde(flatten)]
+    event: &'a Event,
+}
+
+/// Start writing events to stdout
+pub fn enable() {
+    ENABLED.store(true, Ordering::Relaxed);
+}
+
+/// Write an event to stdout if the event stream is enabled
+pub fn emit(event: Event) {
+    if !ENABLED.load(Ordering::Relaxed) {
+        return;
+    }
+
+    let line = to_line(&event, unix_now());
+    let mut stdout = std::io::stdout().lock();
+    let _ = writeln!(stdout, "{}", line);
+    let _ = stdout.flush();
+}
+
+fn to_line(event: &Event, timestamp: i64) -> String {
+    serde_json::to_string(&Envelope {
+        version: EVENT_SCHEMA_VERSION,
+        timestamp,
+        event,
+    })
+    .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::Value;
+
+    #[test]
+    fn test_event_lines() {
+        assert_eq!(
+            to_line(&Event::ProcessDetected { process: ProcessKind::Game }, 100),
+            r#"{"version":1,"timestamp":100,"event":"process_detected","process":"game"}"#
+        );
+        assert_eq!(
+            to_line(&Event::DiscordConnected, 100),
+            r#"{"version":1,"timestamp":100,"event":"discord_connected"}"#
+        );
+
+        let transition = Transition {
+            from: GameState::MainMenu,
+            to: GameState::Singleplayer {
+                world_name: "Orbis".to_string(),
+            },
+            cause: Cause::InGame,
+            at: 90,
+        };
+        let line: Value = serde_json::from_str(&to_line(&Event::from(&transition), 100)).unwrap();
+        assert_eq!(line["event"], "state_transition");
+        assert_eq!(line["from"]["state"], "main_menu");
+        assert_eq!(line["to"]["world_name"], "Orbis");
+        assert_eq!(line["cause"], "in_game");
+        assert_eq!(line["at"], 90);
+        assert_eq!(line["timestamp"], 100);
+    }
+}
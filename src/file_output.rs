@@ -0,0 +1,195 @@
+//! Text and JSON file output for streaming software
+//!
+//! Writes the rendered presence strings to files that OBS text sources (or
+//! any other tool) can read. Every write goes to a temporary file that is
+//! then renamed over the target, so readers never see a half-written file.
+
+use std::fs::{self, File};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, Result};
+use log::debug;
+use serde::Serialize;
+
+use crate::config::{AppConfig, GameState};
+use crate::sink::{PresenceSink, PresenceUpdate};
+
+/// File output writer, remembering what was last written
+pub struct FileOutput {
+    last_text: Option<String>,
+    last_json: Option<String>,
+}
+
+impl FileOutput {
+    /// Create a new file output writer
+    pub fn new() -> Self {
+        Self {
+            last_text: None,
+            last_json: None,
+        }
+    }
+
+    /// Write the outputs for the given state, skipping files that wouldn't change
+    pub fn write(
+        &mut self,
+        state: &GameState,
+        session_start: Option<i64>,
+        config: &AppConfig,
+    ) -> Result<()> {
+        let output = &config.file_output;
+
+        if let Some(ref path) = output.text_path {
+            let text = if matches!(state, GameState::Unknown) {
+                output.idle_template.clone()
+            } else {
+                render_template(&output.text_template, state, config)
+            };
+            if self.last_text.as_ref() != Some(&text) {
+                write_atomic(path, text.as_bytes())
+                    .with_context(|| format!("Failed to write {}", path.display()))?;
+                debug!("Wrote text output: {}", text);
+                self.last_text = Some(text);
+            }
+        }
+
+        if let Some(ref path) = output.json_path {
+            let json = render_json(state, session_start, config)?;
+            if self.last_json.as_ref() != Some(&json) {
+                write_atomic(path, json.as_bytes())
+                    .with_context(|| format!("Failed to write {}", path.display()))?;
+                self.last_json = Some(json);
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl PresenceSink for FileOutput {
+    fn name(&self) -> &'static str {
+        "File"
+    }
+
+    fn update(&mut self, update: &PresenceUpdate) -> Result<()> {
+        self.write(update.state, update.session_start, update.config)
+    }
+
+    fn clear(&mut self, config: &AppConfig) -> Result<()> {
+        self.write(&GameState::Unknown, None, config)
+    }
+}
+
+impl Default for FileOutput {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Render a text template using the same strings as the Discord presence
+pub fn render_template(template: &str, state: &GameState, config: &AppConfig) -> String {
+    template
+        .replace("{details}", state.details())
+        .replace("{state}", &state.state(config))
+        .replace("{world}", state.shown_world(config).unwrap_or_default())
+        .replace("{server}", state.shown_server(config).unwrap_or_default())
+}
+
+/// JSON output: the game state, redacted by the privacy toggles, plus the
+/// rendered strings
+#[derive(Serialize)]
+struct JsonOutput<'a> {
+    running: bool,
+    details: Option<&'a str>,
+    text: Option<String>,
+    #[serde(flatten)]
+    state: GameState,
+    session_start: Option<i64>,
+}
+
+fn render_json(state: &GameState, session_start: Option<i64>, config: &AppConfig) -> Result<String> {
+    let running = !matches!(state, GameState::Unknown);
+    let output = JsonOutput {
+        running,
+        details: running.then(|| state.details()),
+        text: running.then(|| state.state(config)),
+        state: state.redacted(config),
+        session_start,
+    };
+    Ok(serde_json::to_string_pretty(&output)?)
+}
+
+/// Write a file atomically by writing a sibling temp file and renaming it
+pub(crate) fn write_atomic(path: &Path, contents: &[u8]) -> std::io::Result<()> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+
+    let mut tmp_path = path.as_os_str().to_owned();
+    tmp_path.push(".tmp");
+    let tmp_path = PathBuf::from(tmp_path);
+
+    {
+        let mut file = File::create(&tmp_path)?;
+        file.write_all(contents)?;
+        file.sync_all()?;
+    }
+
+    fs::rename(&tmp_path, path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::Value;
+
+    #[test]
+    fn test_render_template() {
+        let mut config = AppConfig::default();
+        let state = GameState::Multiplayer {
+            server_address: Some("play.example.com:5520".to_string()),
+            server_name: Some("Example SMP".to_string()),
+        };
+
+        assert_eq!(
+            render_template("Playing on {server} ({details})", &state, &config),
+            "Playing on Example SMP (Playing Multiplayer)"
+        );
+
+        config.show_server_ip = false;
+        assert_eq!(
+            render_template("{details} - {state} {server}", &state, &config),
+            "Playing Multiplayer - Online "
+        );
+    }
+
+    #[test]
+    fn test_update_writes_files() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut config = AppConfig::default();
+        config.file_output.text_path = Some(dir.path().join("out/now-playing.txt"));
+        config.file_output.json_path = Some(dir.path().join("out/now-playing.json"));
+
+        let mut output = FileOutput::new();
+        let state = GameState::Singleplayer {
+            world_name: "Orbis".to_string(),
+        };
+        output.write(&state, Some(100), &config).unwrap();
+
+        let text = fs::read_to_string(dir.path().join("out/now-playing.txt")).unwrap();
+        assert_eq!(text, "Playing Singleplayer - World: Orbis");
+
+        let json: Value =
+            serde_json::from_str(&fs::read_to_string(dir.path().join("out/now-playing.json")).unwrap())
+                .unwrap();
+        assert_eq!(json["state"], "singleplayer");
+        assert_eq!(json["world_name"], "Orbis");
+        assert_eq!(json["text"], "World: Orbis");
+        assert_eq!(json["session_start"], 100);
+        assert!(!dir.path().join("out/now-playing.txt.tmp").exists());
+
+        output.clear(&config).unwrap();
+        let text = fs::read_to_string(dir.path().join("out/now-playing.txt")).unwrap();
+        assert_eq!(text, "");
+    }
+}
@@ -0,0 +1,152 @@
+//! Warnings and errors logged by the game client
+//!
+//! The log watcher counts `WARN` and `ERROR`/`FATAL` lines per game session,
+//! grouped by source (the third field of a log line), and keeps the most
+//! recent ones, so asset or network problems show up without opening the
+//! log.
+
+use std::collections::{BTreeMap, VecDeque};
+
+use serde::{Deserialize, Serialize};
+
+use crate::log_entry::{LogEntry, LogLevel};
+
+/// Number of recent warning and error messages kept
+pub const RECENT_MESSAGES: usize = 10;
+
+/// Source used for lines without one
+const UNKNOWN_SOURCE: &str = "(unknown)";
+
+/// Warning and error counts
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Counts {
+    pub warnings: u64,
+    pub errors: u64,
+}
+
+impl Counts {
+    fn add(&mut self, level: LogLevel) {
+        if level >= LogLevel::Error {
+            self.errors += 1;
+        } else {
+            self.warnings += 1;
+        }
+    }
+
+    /// e.g. `2 errors, 1 warning`
+    pub fn summary(&self) -> String {
+        let plural = |n: u64, word: &str| format!("{} {}{}", n, word, if n == 1 { "" } else { "s" });
+        format!("{}, {}", plural(self.errors, "error"), plural(self.warnings, "warning"))
+    }
+}
+
+/// A warning or error line
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct HealthMessage {
+    /// Unix timestamp (seconds) of the line, if it had one
+    pub timestamp: Option<i64>,
+    pub level: LogLevel,
+    pub source: String,
+    pub message: String,
+}
+
+/// Warnings and errors of the current game session
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Health {
+    pub total: Counts,
+    pub sources: BTreeMap<String, Counts>,
+    /// Most recent messages, oldest first
+    pub recent: VecDeque<HealthMessage>,
+}
+
+impl Health {
+    /// Create an empty health record
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Count the entry if it is a warning or an error
+    pub fn record(&mut self, entry: &LogEntry) {
+        let Some(level) = entry.level.filter(|level| *level >= LogLevel::Warn) else {
+            return;
+        };
+        let source = entry.source.as_deref().unwrap_or(UNKNOWN_SOURCE);
+
+        self.total.add(level);
+        self.sources.entry(source.to_string()).or_default().add(level);
+
+        if self.recent.len() == RECENT_MESSAGES {
+            self.recent.pop_front();
+        }
+        self.recent.push_back(HealthMessage {
+            timestamp: entry.unix_timestamp(),
+            level,
+            source: source.to_string(),
+            message: entry.message.clone(),
+        });
+    }
+
+    /// Sources with the most errors (then warnings) first
+    pub fn worst_sources(&self) -> Vec<(&str, Counts)> {
+        let mut sources: Vec<(&str, Counts)> = self
+            .sources
+            .iter()
+            .map(|(source, counts)| (source.as_str(), *counts))
+            .collect();
+        sources.sort_by_key(|(_, counts)| std::cmp::Reverse((counts.errors, counts.warnings)));
+        sources
+    }
+
+    /// Human-readable lines: one per source, then the recent messages
+    pub fn report_lines(&self) -> Vec<String> {
+        let mut lines: Vec<String> = self
+            .worst_sources()
+            .into_iter()
+            .map(|(source, counts)| format!("{}: {}", source, counts.summary()))
+            .collect();
+        for message in self.recent.iter().rev() {
+            let level = format!("{:?}", message.level).to_uppercase();
+            lines.push(format!("{} {}: {}", level, message.source, message.message));
+        }
+        lines
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_record() {
+        let mut health = Health::new();
+        for line in [
+            "2026-01-25 11:00:00.0000|INFO|App|fine",
+            "2026-01-25 11:00:01.0000|WARN|Assets|Missing texture a",
+            "2026-01-25 11:00:02.0000|WARN|Assets|Missing texture b",
+            "2026-01-25 11:00:03.0000|ERROR|Network|Connection reset",
+            "legacy line without a level",
+        ] {
+            health.record(&LogEntry::parse(line).unwrap());
+        }
+
+        assert_eq!(
+            health.total,
+            Counts {
+                warnings: 2,
+                errors: 1
+            }
+        );
+        assert_eq!(health.total.summary(), "1 error, 2 warnings");
+        let sources: Vec<&str> = health.worst_sources().iter().map(|(s, _)| *s).collect();
+        assert_eq!(sources, ["Network", "Assets"]);
+        assert_eq!(health.recent.len(), 3);
+        assert_eq!(health.recent.back().unwrap().message, "Connection reset");
+
+        for _ in 0..RECENT_MESSAGES {
+            health.record(&LogEntry::parse("x|ERROR|Net|again").unwrap());
+        }
+        assert_eq!(health.recent.len(), RECENT_MESSAGES);
+        assert_eq!(health.total.errors, 1 + RECENT_MESSAGES as u64);
+        assert_eq!(health.report_lines()[0], "Net: 10 errors, 0 warnings");
+    }
+}
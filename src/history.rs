@@ -0,0 +1,116 @@
+//! Local history of game sessions
+//!
+//! Every finished game session is appended to `history.jsonl` in the data
+//! directory, one JSON object per line.
+
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::path::PathBuf;
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+
+use crate::config::get_data_dir;
+use crate::resources::ResourceSummary;
+
+/// File name of the history in the data directory
+pub const HISTORY_FILE: &str = "history.jsonl";
+
+/// One game session, from the client starting to it closing
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SessionRecord {
+    /// Unix timestamps (seconds)
+    pub start: i64,
+    pub end: i64,
+    pub resources: Option<ResourceSummary>,
+}
+
+/// Append-only store of finished sessions
+pub struct SessionHistory {
+    path: PathBuf,
+}
+
+impl SessionHistory {
+    /// Use the history file in the data directory
+    pub fn new() -> Self {
+        Self::with_path(get_data_dir().join(HISTORY_FILE))
+    }
+
+    /// Use the history file at `path`
+    pub fn with_path(path: PathBuf) -> Self {
+        Self { path }
+    }
+
+    /// Append a finished session
+    pub fn append(&self, record: &SessionRecord) -> Result<()> {
+        if let Some(parent) = self.path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        let mut file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)
+            .with_context(|| format!("Failed to open {}", self.path.display()))?;
+        writeln!(file, "{}", serde_json::to_string(record)?)?;
+        Ok(())
+    }
+}
+
+impl Default for SessionHistory {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::resources::Range;
+
+    impl SessionHistory {
+        /// Read all recorded sessions, skipping lines that can't be parsed
+        fn load(&self) -> Result<Vec<SessionRecord>> {
+            if !self.path.exists() {
+                return Ok(Vec::new());
+            }
+            let content = fs::read_to_string(&self.path)?;
+            Ok(content
+                .lines()
+                .filter_map(|line| serde_json::from_str(line).ok())
+                .collect())
+        }
+    }
+
+    #[test]
+    fn test_append_and_load() {
+        let dir = tempfile::tempdir().unwrap();
+        let history = SessionHistory::with_path(dir.path().join("sub").join(HISTORY_FILE));
+        assert!(history.load().unwrap().is_empty());
+
+        let range = Range {
+            min: 1.0,
+            avg: 2.0,
+            max: 3.0,
+        };
+        let records = [
+            SessionRecord {
+                start: 100,
+                end: 200,
+                resources: None,
+            },
+            SessionRecord {
+                start: 300,
+                end: 400,
+                resources: Some(ResourceSummary {
+                    samples: 3,
+                    cpu_percent: range,
+                    memory_bytes: range,
+                }),
+            },
+        ];
+        for record in &records {
+            history.append(record).unwrap();
+        }
+        assert_eq!(history.load().unwrap(), records);
+    }
+}
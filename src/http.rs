@@ -0,0 +1,555 @@
+//! Local HTTP status endpoint and Server-Sent Events stream
+//!
+//! Serves `GET /status` with the current game state and `GET /events` as an
+//! SSE stream of state transitions, plus optional Prometheus metrics on
+//! `GET /metrics`. The server only ever binds to 127.0.0.1.
+
+use std::fmt::Write as _;
+use std::io::{BufRead, BufReader, Read, Write};
+use std::net::{Ipv4Addr, SocketAddr, TcpListener, TcpStream};
+use std::sync::mpsc::{self, RecvTimeoutError, Sender};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::Duration;
+
+use anyhow::{Context, Result};
+use log::{debug, warn};
+use serde::Serialize;
+use serde_json::{json, Value};
+
+use crate::config::{AppConfig, GameState, HttpConfig};
+use crate::health::Health;
+use crate::log_watcher::unix_now;
+use crate::metrics;
+use crate::resources::ResourceUsage;
+use crate::sink::{PresenceSink, PresenceUpdate};
+use crate::state_machine::{Cause, Transition};
+
+/// Interval between SSE keep-alive comments
+const KEEP_ALIVE_INTERVAL: Duration = Duration::from_secs(15);
+
+/// Maximum size of a request head we are willing to read
+const MAX_REQUEST_HEAD: u64 = 8 * 1024;
+
+/// What is currently being played
+#[derive(Debug, Clone, Default)]
+struct Status {
+    state: GameState,
+    session_start: Option<i64>,
+    resources: Option<ResourceUsage>,
+    health: Health,
+}
+
+/// State shared between the main loop and connection threads
+struct Shared {
+    status: Mutex<Status>,
+    subscribers: Mutex<Vec<Sender<String>>>,
+    token: Option<String>,
+    metrics: bool,
+}
+
+/// Local HTTP server exposing the current status
+pub struct StatusServer {
+    shared: Arc<Shared>,
+    local_addr: SocketAddr,
+}
+
+impl StatusServer {
+    /// Bind to 127.0.0.1 and start serving in a background thread
+    pub fn start(config: &HttpConfig) -> Result<Self> {
+        let listener = TcpListener::bind((Ipv4Addr::LOCALHOST, config.port))
+            .with_context(|| format!("Failed to bind HTTP server to port {}", config.port))?;
+        let local_addr = listener.local_addr()?;
+
+        let shared = Arc::new(Shared {
+            status: Mutex::new(Status::default()),
+            subscribers: Mutex::new(Vec::new()),
+            token: config.token.clone().filter(|t| !t.is_empty()),
+            metrics: config.metrics,
+        });
+
+        let accept_shared = shared.clone();
+        thread::spawn(move || {
+            for stream in listener.incoming() {
+                match stream {
+                    Ok(stream) => {
+                        let shared = accept_shared.clone();
+                        thread::spawn(move || {
+                            if let Err(e) = handle_connection(stream, &shared) {
+                                debug!("HTTP connection error: {}", e);
+                            }
+                        });
+                    }
+                    Err(e) => warn!("HTTP accept failed: {}", e),
+                }
+            }
+        });
+
+        Ok(Self { shared, local_addr })
+    }
+
+    /// Address the server is bound to
+    pub fn local_addr(&self) -> SocketAddr {
+        self.local_addr
+    }
+
+    /// Update the status served by `/status`
+    pub fn set_status(&self, state: &GameState, session_start: Option<i64>) {
+        let mut status = self.shared.status.lock().unwrap();
+        status.state = state.clone();
+        status.session_start = session_start;
+    }
+
+    /// Update the warnings and errors served by `/status`
+    pub fn set_health(&self, health: &Health) {
+        self.shared.status.lock().unwrap().health = health.clone();
+    }
+
+    /// Update the game resource use served by `/status`
+    pub fn set_resources(&self, resources: Option<ResourceUsage>) {
+        self.shared.status.lock().unwrap().resources = resources;
+    }
+
+    /// Push a transition to all `/events` subscribers
+    pub fn publish(&self, transition: &Transition) {
+        let event = sse_event("transition", &json!(transition));
+        self.shared
+            .subscribers
+            .lock()
+            .unwrap()
+            .retain(|tx| tx.send(event.clone()).is_ok());
+    }
+}
+
+impl PresenceSink for StatusServer {
+    fn name(&self) -> &'static str {
+        "HTTP"
+    }
+
+    fn update(&mut self, update: &PresenceUpdate) -> Result<()> {
+        for transition in update.transitions {
+            self.publish(&Transition {
+                from: transition.from.redacted(update.config),
+                to: transition.to.redacted(update.config),
+                ..transition.clone()
+            });
+        }
+        self.set_status(&update.state.redacted(update.config), update.session_start);
+        self.set_resources(update.resources.filter(|_| update.config.resources.http));
+        self.set_health(update.health);
+        Ok(())
+    }
+
+    fn clear(&mut self, _config: &AppConfig) -> Result<()> {
+        let from = self.shared.status.lock().unwrap().state.clone();
+        if from != GameState::Unknown {
+            self.publish(&Transition {
+                from,
+                to: GameState::Unknown,
+                cause: Cause::Cleared,
+                at: unix_now(),
+            });
+        }
+        self.set_status(&GameState::Unknown, None);
+        self.set_resources(None);
+        self.set_health(&Health::new());
+        Ok(())
+    }
+
+    /// Hide the status without a `Cleared` event: the session goes on, and
+    /// `/events` gets the transitions that happened meanwhile on resuming
+    fn pause(&mut self, _config: &AppConfig) -> Result<()> {
+        self.set_status(&GameState::Unknown, None);
+        self.set_resources(None);
+        Ok(())
+    }
+}
+
+/// Ask a running instance for its status and format it for the terminal
+pub fn status_report(config: &HttpConfig) -> Result<String> {
+    let url = format!("http://{}:{}/status", Ipv4Addr::LOCALHOST, config.port);
+    let mut request = ureq::get(&url).timeout(Duration::from_secs(5));
+    if let Some(token) = config.token.as_deref().filter(|t| !t.is_empty()) {
+        request = request.set("Authorization", &format!("Bearer {}", token));
+    }
+    let status: Value = request
+        .call()
+        .with_context(|| format!("No running instance answered on {}", url))?
+        .into_json()?;
+
+    let state: GameState = serde_json::from_value(status.clone())
+        .context("Unexpected status from the running instance")?;
+    let mut out = String::new();
+    let _ = writeln!(out, "State: {}", state.details());
+    if let Some(world) = state.world_name() {
+        let _ = writeln!(out, "World: {}", world);
+    }
+    if let Some(server) = state.server_name().or_else(|| state.server_address()) {
+        let _ = writeln!(out, "Server: {}", server);
+    }
+
+    let health: Health = serde_json::from_value(status["health"].clone()).unwrap_or_default();
+    let _ = writeln!(out, "Health: {}", health.total.summary());
+    for line in health.report_lines() {
+        let _ = writeln!(out, "  {}", line);
+    }
+    Ok(out)
+}
+
+/// A parsed HTTP request head
+struct Request {
+    method: String,
+    target: String,
+    headers: Vec<(String, String)>,
+}
+
+impl Request {
+    /// Request path without the query string
+    fn path(&self) -> &str {
+        self.target.split('?').next().unwrap_or("")
+    }
+
+    fn header(&self, name: &str) -> Option<&str> {
+        self.headers
+            .iter()
+            .find(|(k, _)| k.eq_ignore_ascii_case(name))
+            .map(|(_, v)| v.as_str())
+    }
+}
+
+fn read_request(stream: &TcpStream) -> Result<Request> {
+    let mut reader = BufReader::new(stream.take(MAX_REQUEST_HEAD));
+
+    let mut request_line = String::new();
+    reader.read_line(&mut request_line)?;
+    let mut parts = request_line.split_whitespace();
+    let method = parts.next().context("Empty request")?.to_string();
+    let target = parts.next().context("Missing request target")?.to_string();
+
+    let mut headers = Vec::new();
+    loop {
+        let mut line = String::new();
+        if reader.read_line(&mut line)? == 0 {
+            break;
+        }
+        let line = line.trim_end();
+        if line.is_empty() {
+            break;
+        }
+        if let Some((name, value)) = line.split_once(':') {
+            headers.push((name.trim().to_string(), value.trim().to_string()));
+        }
+    }
+
+    Ok(Request {
+        method,
+        target,
+        headers,
+    })
+}
+
+fn is_authorized(request: &Request, token: Option<&str>) -> bool {
+    let Some(token) = token else {
+        return true;
+    };
+    request
+        .header("Authorization")
+        .and_then(|v| v.strip_prefix("Bearer "))
+        .map(|v| v.trim() == token)
+        .unwrap_or(false)
+}
+
+fn handle_connection(mut stream: TcpStream, shared: &Shared) -> Result<()> {
+    stream.set_read_timeout(Some(Duration::from_secs(5)))?;
+    let request = read_request(&stream)?;
+    debug!("HTTP {} {}", request.method, request.target);
+
+    if !is_authorized(&request, shared.token.as_deref()) {
+        return write_response(
+            &mut stream,
+            "401 Unauthorized",
+            &json!({ "error": "unauthorized" }),
+        );
+    }
+
+    match (request.method.as_str(), request.path()) {
+        ("GET", "/status") => {
+            let body = status_json(&shared.status.lock().unwrap());
+            write_response(&mut stream, "200 OK", &body)
+        }
+        ("GET", "/events") => stream_events(stream, shared),
+        ("GET", "/metrics") if shared.metrics => write_raw(
+            &mut stream,
+            "200 OK",
+            "text/plain; version=0.0.4",
+            &metrics::global().render(),
+        ),
+        ("GET", _) => write_response(&mut stream, "404 Not Found", &json!({ "error": "not found" })),
+        _ => write_response(
+            &mut stream,
+            "405 Method Not Allowed",
+            &json!({ "error": "method not allowed" }),
+        ),
+    }
+}
+
+fn write_response(stream: &mut TcpStream, status: &str, body: &Value) -> Result<()> {
+    write_raw(stream, status, "application/json", &body.to_string())
+}
+
+fn write_raw(stream: &mut TcpStream, status: &str, content_type: &str, body: &str) -> Result<()> {
+    let mut head = format!(
+        "HTTP/1.1 {}\r\nContent-Type: {}\r\nContent-Length: {}\r\nConnection: close\r\n",
+        status,
+        content_type,
+        body.len()
+    );
+    if status.starts_with("401") {
+        head.push_str("WWW-Authenticate: Bearer\r\n");
+    }
+    head.push_str("\r\n");
+
+    stream.write_all(head.as_bytes())?;
+    stream.write_all(body.as_bytes())?;
+    stream.flush()?;
+    Ok(())
+}
+
+/// Serve `/events` until the client goes away
+fn stream_events(mut stream: TcpStream, shared: &Shared) -> Result<()> {
+    let (tx, rx) = mpsc::channel();
+    shared.subscribers.lock().unwrap().push(tx);
+
+    stream.write_all(
+        b"HTTP/1.1 200 OK\r\nContent-Type: text/event-stream\r\nCache-Control: no-cache\r\nConnection: keep-alive\r\n\r\n",
+    )?;
+
+    // Start with the current status so clients don't have to poll `/status` first
+    let initial = sse_event("status", &status_json(&shared.status.lock().unwrap()));
+    stream.write_all(initial.as_bytes())?;
+    stream.flush()?;
+
+    loop {
+        let chunk = match rx.recv_timeout(KEEP_ALIVE_INTERVAL) {
+            Ok(event) => event,
+            Err(RecvTimeoutError::Timeout) => ": keep-alive\n\n".to_string(),
+            Err(RecvTimeoutError::Disconnected) => return Ok(()),
+        };
+        if stream.write_all(chunk.as_bytes()).is_err() || stream.flush().is_err() {
+            debug!("SSE client disconnected");
+            return Ok(());
+        }
+    }
+}
+
+fn sse_event(name: &str, data: &Value) -> String {
+    format!("event: {}\ndata: {}\n\n", name, data)
+}
+
+/// `/status` body: the serialized game state plus session details
+#[derive(Serialize)]
+struct StatusBody<'a> {
+    #[serde(flatten)]
+    state: &'a GameState,
+    session_start: Option<i64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    resources: Option<ResourceUsage>,
+    health: &'a Health,
+}
+
+fn status_json(status: &Status) -> Value {
+    serde_json::to_value(StatusBody {
+        state: &status.state,
+        session_start: status.session_start,
+        resources: status.resources,
+        health: &status.health,
+    })
+    .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::log_entry::LogEntry;
+
+    fn start_test_server(token: Option<&str>) -> StatusServer {
+        StatusServer::start(&HttpConfig {
+            enabled: true,
+            port: 0,
+            token: token.map(|t| t.to_string()),
+            metrics: true,
+        })
+        .unwrap()
+    }
+
+    fn get(server: &StatusServer, path: &str, token: Option<&str>) -> String {
+        let mut stream = TcpStream::connect(server.local_addr()).unwrap();
+        let mut request = format!("GET {} HTTP/1.1\r\nHost: localhost\r\n", path);
+        if let Some(token) = token {
+            request.push_str(&format!("Authorization: Bearer {}\r\n", token));
+        }
+        request.push_str("\r\n");
+        stream.write_all(request.as_bytes()).unwrap();
+
+        let mut response = String::new();
+        stream.read_to_string(&mut response).unwrap();
+        response
+    }
+
+    #[test]
+    fn test_status_endpoint() {
+        let server = start_test_server(Some("secret"));
+        server.set_status(
+            &GameState::Multiplayer {
+                server_address: Some("play.example.com:5520".to_string()),
+                server_name: None,
+            },
+            Some(1_700_000_000),
+        );
+
+        let denied = get(&server, "/status", None);
+        assert!(denied.starts_with("HTTP/1.1 401"));
+
+        let response = get(&server, "/status", Some("secret"));
+        assert!(response.starts_with("HTTP/1.1 200"));
+        let body: Value = serde_json::from_str(response.split("\r\n\r\n").nth(1).unwrap()).unwrap();
+        assert_eq!(body["state"], "multiplayer");
+        assert_eq!(body["server_address"], "play.example.com:5520");
+        assert_eq!(body["session_start"], 1_700_000_000);
+        assert!(body.get("resources").is_none());
+
+        server.set_resources(Some(ResourceUsage {
+            cpu_percent: 25.0,
+            memory_bytes: 2_000_000_000,
+            uptime_secs: 60,
+        }));
+        let response = get(&server, "/status", Some("secret"));
+        let body: Value = serde_json::from_str(response.split("\r\n\r\n").nth(1).unwrap()).unwrap();
+        assert_eq!(body["resources"]["memory_bytes"], 2_000_000_000u64);
+
+        assert!(get(&server, "/nope", Some("secret")).starts_with("HTTP/1.1 404"));
+
+        let metrics = get(&server, "/metrics", Some("secret"));
+        assert!(metrics.starts_with("HTTP/1.1 200"));
+        assert!(metrics.contains("# TYPE hytale_rpc_state_seconds_total counter"));
+    }
+
+    #[test]
+    fn test_status_honours_privacy() {
+        let mut server = start_test_server(None);
+        let config = AppConfig {
+            show_server_ip: false,
+            ..Default::default()
+        };
+        let state = GameState::Multiplayer {
+            server_address: Some("play.example.com:5520".to_string()),
+            server_name: Some("Example SMP".to_string()),
+        };
+        server
+            .update(&PresenceUpdate {
+                state: &state,
+                session_start: None,
+                game_running: true,
+                game_started_at: None,
+                transitions: &[],
+                resources: None,
+                health: &Health::new(),
+                config: &config,
+            })
+            .unwrap();
+
+        let response = get(&server, "/status", None);
+        let body: Value = serde_json::from_str(response.split("\r\n\r\n").nth(1).unwrap()).unwrap();
+        assert_eq!(body["state"], "multiplayer");
+        assert!(body["server_address"].is_null());
+        assert!(body["server_name"].is_null());
+    }
+
+    #[test]
+    fn test_pause_hides_status_without_event() {
+        let mut server = start_test_server(None);
+        let (tx, rx) = mpsc::channel();
+        server.shared.subscribers.lock().unwrap().push(tx);
+        server.set_status(&GameState::MainMenu, Some(1_700_000_000));
+
+        server.pause(&AppConfig::default()).unwrap();
+
+        let response = get(&server, "/status", None);
+        let body: Value = serde_json::from_str(response.split("\r\n\r\n").nth(1).unwrap()).unwrap();
+        assert_eq!(body["state"], "unknown");
+        assert!(body["session_start"].is_null());
+        assert!(rx.try_recv().is_err());
+    }
+
+    #[test]
+    fn test_status_report() {
+        let server = start_test_server(Some("secret"));
+        server.set_status(
+            &GameState::Singleplayer {
+                world_name: "Orbis".to_string(),
+            },
+            None,
+        );
+        let mut health = Health::new();
+        health.record(&LogEntry::parse("2026-01-25 11:00:00.0000|ERROR|Network|Connection reset").unwrap());
+        server.set_health(&health);
+
+        let config = HttpConfig {
+            port: server.local_addr().port(),
+            token: Some("secret".to_string()),
+            ..Default::default()
+        };
+        let report = status_report(&config).unwrap();
+        assert!(report.contains("State: Playing Singleplayer\n"));
+        assert!(report.contains("World: Orbis\n"));
+        assert!(report.contains("Health: 1 error, 0 warnings\n"));
+        assert!(report.contains("  Network: 1 error, 0 warnings\n"));
+        assert!(report.contains("  ERROR Network: Connection reset\n"));
+    }
+
+    #[test]
+    fn test_events_stream() {
+        let server = start_test_server(None);
+        let mut stream = TcpStream::connect(server.local_addr()).unwrap();
+        stream
+            .write_all(b"GET /events HTTP/1.1\r\nHost: localhost\r\n\r\n")
+            .unwrap();
+        let mut reader = BufReader::new(stream);
+
+        // Skip response head and the initial status event
+        let mut line = String::new();
+        loop {
+            line.clear();
+            reader.read_line(&mut line).unwrap();
+            if line.starts_with("event: status") {
+                break;
+            }
+        }
+        reader.read_line(&mut line).unwrap();
+
+        // Wait until the subscriber is registered before publishing
+        while server.shared.subscribers.lock().unwrap().is_empty() {
+            thread::sleep(Duration::from_millis(10));
+        }
+        server.publish(&Transition {
+            from: GameState::MainMenu,
+            to: GameState::Singleplayer {
+                world_name: "Orbis".to_string(),
+            },
+            cause: Cause::InGame,
+            at: 42,
+        });
+
+        loop {
+            line.clear();
+            reader.read_line(&mut line).unwrap();
+            if line.starts_with("data:") {
+                break;
+            }
+        }
+        let data: Value = serde_json::from_str(line.trim_start_matches("data:").trim()).unwrap();
+        assert_eq!(data["from"]["state"], "main_menu");
+        assert_eq!(data["to"]["world_name"], "Orbis");
+        assert_eq!(data["at"], 42);
+    }
+}
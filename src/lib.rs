@@ -0,0 +1,56 @@
+//! Hytale Discord Rich Presence - Rust Implementation
+//!
+//! Library behind the `hytale-rpc` binary. Other tools can reuse the log
+//! parser ([`LogWatcher`], [`LogPatterns`]), the [`GameState`] model, process
+//! detection ([`ProcessDetector`]) and the [`PresenceSink`] output trait.
+//! [`PresenceUpdate`], [`Transition`], [`Cause`] and [`AppConfig`] are
+//! exported because those use them; everything else is internal to the app.
+//!
+//! Cargo features (all enabled by default):
+//! - `discord`: Discord Rich Presence output
+//! - `tray`: system tray icon
+//! - `notifications`: desktop notifications
+//! - `dbus`: D-Bus service on Linux
+
+mod anonymize;
+mod app;
+mod capture;
+mod config;
+mod crash;
+#[cfg(all(feature = "dbus", target_os = "linux"))]
+mod dbus_service;
+mod doctor;
+mod events;
+mod file_output;
+mod health;
+mod history;
+mod http;
+mod log_entry;
+mod log_watcher;
+mod metrics;
+mod mqtt;
+mod process;
+mod resources;
+#[cfg(feature = "discord")]
+mod rpc;
+mod sink;
+mod state_machine;
+mod tray;
+mod webhook;
+
+pub use config::{AppConfig, GameState};
+pub use log_watcher::{LogPatterns, LogWatcher};
+pub use process::ProcessDetector;
+pub use sink::{PresenceSink, PresenceUpdate};
+pub use state_machine::{Cause, Transition};
+
+/// What the `hytale-rpc` binary and the golden tests use; not a stable API
+#[doc(hidden)]
+pub mod internal {
+    pub use crate::anonymize::{anonymize_file, render_timeline, timeline};
+    pub use crate::app::App;
+    pub use crate::crash::analyze as analyze_exit;
+    pub use crate::doctor::report as doctor_report;
+    pub use crate::events::enable as enable_event_stream;
+    pub use crate::http::status_report;
+}
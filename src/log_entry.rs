@@ -0,0 +1,123 @@
+//! Typed representation of a single Hytale client log line
+//!
+//! Current clients write `Timestamp|Level|Source|Message`, e.g.
+//! `2026-01-25 11:06:22.6288|INFO|HytaleClient.Application.Program|...`.
+//! Older lines without that structure are kept as a bare message.
+
+use chrono::{DateTime, Local, NaiveDateTime, TimeZone};
+
+/// Timestamp format of the pipe-delimited log lines (local time)
+const TIMESTAMP_FORMAT: &str = "%Y-%m-%d %H:%M:%S%.f";
+
+/// Severity of a log line
+#[derive(
+    Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, serde::Serialize, serde::Deserialize,
+)]
+#[serde(rename_all = "lowercase")]
+pub enum LogLevel {
+    Trace,
+    Debug,
+    Info,
+    Warn,
+    Error,
+    Fatal,
+}
+
+impl LogLevel {
+    /// Parse a level as written in the log, case-insensitively
+    pub fn parse(level: &str) -> Option<Self> {
+        match level.trim().to_ascii_uppercase().as_str() {
+            "TRACE" => Some(LogLevel::Trace),
+            "DEBUG" => Some(LogLevel::Debug),
+            "INFO" => Some(LogLevel::Info),
+            "WARN" | "WARNING" => Some(LogLevel::Warn),
+            "ERROR" => Some(LogLevel::Error),
+            "FATAL" | "CRITICAL" => Some(LogLevel::Fatal),
+            _ => None,
+        }
+    }
+}
+
+/// A parsed log line
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LogEntry {
+    pub timestamp: Option<DateTime<Local>>,
+    pub level: Option<LogLevel>,
+    pub source: Option<String>,
+    pub message: String,
+}
+
+impl LogEntry {
+    /// Parse a raw log line. Returns `None` for blank lines.
+    pub fn parse(raw_line: &str) -> Option<Self> {
+        let raw_line = raw_line.trim();
+        if raw_line.is_empty() {
+            return None;
+        }
+
+        let parts: Vec<&str> = raw_line.splitn(4, '|').collect();
+        if let [timestamp, level, source, message] = parts[..] {
+            return Some(Self {
+                timestamp: parse_timestamp(timestamp),
+                level: LogLevel::parse(level),
+                source: Some(source.trim().to_string()).filter(|s| !s.is_empty()),
+                message: message.trim().to_string(),
+            });
+        }
+
+        // Legacy line: the whole line is the message
+        Some(Self {
+            timestamp: None,
+            level: None,
+            source: None,
+            message: raw_line.to_string(),
+        })
+    }
+
+    /// Unix timestamp (seconds) of the line, if it has one
+    pub fn unix_timestamp(&self) -> Option<i64> {
+        self.timestamp.map(|t| t.timestamp())
+    }
+}
+
+fn parse_timestamp(timestamp: &str) -> Option<DateTime<Local>> {
+    let naive = NaiveDateTime::parse_from_str(timestamp.trim(), TIMESTAMP_FORMAT).ok()?;
+    Local.from_local_datetime(&naive).earliest()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::{Datelike, Timelike};
+
+    #[test]
+    fn test_parse_pipe_format() {
+        let entry = LogEntry::parse(
+            "2026-01-25 11:06:22.6288|INFO|HytaleClient.Application.Program|Changing from Stage Startup to MainMenu\n",
+        )
+        .unwrap();
+
+        let timestamp = entry.timestamp.unwrap();
+        assert_eq!((timestamp.year(), timestamp.month(), timestamp.day()), (2026, 1, 25));
+        assert_eq!((timestamp.hour(), timestamp.minute(), timestamp.second()), (11, 6, 22));
+        assert_eq!(entry.level, Some(LogLevel::Info));
+        assert_eq!(entry.source.as_deref(), Some("HytaleClient.Application.Program"));
+        assert_eq!(entry.message, "Changing from Stage Startup to MainMenu");
+    }
+
+    #[test]
+    fn test_parse_legacy_and_malformed_lines() {
+        let legacy = LogEntry::parse("Changing Stage to MainMenu").unwrap();
+        assert_eq!(legacy.timestamp, None);
+        assert_eq!(legacy.level, None);
+        assert_eq!(legacy.message, "Changing Stage to MainMenu");
+
+        // Pipes inside the message are kept
+        let entry = LogEntry::parse("not a time|WARNING|Net|a | b").unwrap();
+        assert_eq!(entry.timestamp, None);
+        assert_eq!(entry.level, Some(LogLevel::Warn));
+        assert_eq!(entry.message, "a | b");
+
+        assert!(LogEntry::parse("   ").is_none());
+    }
+}
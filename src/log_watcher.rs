@@ -1,15 +1,39 @@
 //! Log watcher and parser for Hytale client logs
 
-use std::fs::{self, File};
-use std::io::{BufRead, BufReader, Seek, SeekFrom};
-use std::path::PathBuf;
-use std::time::SystemTime;
+use std::collections::VecDeque;
+use std::fmt;
+use std::fs::{self, File, Metadata};
+use std::io::{BufRead, BufReader, Read, Seek, SeekFrom};
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
 
 use anyhow::{Context, Result};
 use log::{debug, info};
 use regex::Regex;
 
 use crate::config::{get_log_directories, GameState, LOG_FILE_PATTERN};
+use crate::capture::UnknownLines;
+use crate::events::{self, Event};
+use crate::health::Health;
+use crate::process::DetectedProcess;
+use crate::state_machine::{Cause, StateMachine, Transition};
+use crate::log_entry::LogEntry;
+use crate::metrics;
+
+/// Most bytes read from the end of a log when catching up on it
+const CATCH_UP_MAX_BYTES: u64 = 16 * 1024 * 1024;
+
+/// Size of the chunks read while scanning backward
+const CATCH_UP_CHUNK_BYTES: u64 = 64 * 1024;
+
+/// Number of log lines kept for crash reports
+const RECENT_LINES: usize = 200;
+
+/// How long after the game process starts its log file may be created
+const CREATED_WINDOW_SECS: u64 = 300;
+
+/// Allowed clock difference between process start and file creation times
+const CREATED_SLACK_SECS: u64 = 5;
 
 /// Log patterns for detecting game state
 pub struct LogPatterns {
@@ -17,6 +41,7 @@ pub struct LogPatterns {
     singleplayer_world: Regex,
     singleplayer_create: Regex,
     multiplayer_connect: Regex,
+    server_established: Regex,
     server_connect: Regex,
     in_game: Regex,
     world_loaded: Regex,
@@ -24,6 +49,7 @@ pub struct LogPatterns {
     playing_singleplayer: Regex,
     playing_multiplayer: Regex,
     loading_stage: Regex,
+    shutdown: Regex,
 }
 
 impl LogPatterns {
@@ -37,10 +63,9 @@ impl LogPatterns {
                 .unwrap(),
             singleplayer_create: Regex::new(r"Creating new singleplayer world in|Creating world")
                 .unwrap(),
-            multiplayer_connect: Regex::new(
-                r"Connecting to (?:multiplayer|dedicated) server|Server connection established",
-            )
-            .unwrap(),
+            multiplayer_connect: Regex::new(r"Connecting to (?:multiplayer|dedicated) server")
+                .unwrap(),
+            server_established: Regex::new(r"Server connection established").unwrap(),
             server_connect: Regex::new(r"Opening Quic Connection to ([\d\w\.-]+):(\d+)").unwrap(),
             in_game: Regex::new(
                 r"Changing from Stage (?:GameLoading|Loading) to InGame|GameInstance\.StartJoiningWorld|GameInstance\.OnWorldJoined",
@@ -61,6 +86,10 @@ impl LogPatterns {
             )
             .unwrap(),
             loading_stage: Regex::new(r"Changing from loading stage (\w+) to (\w+)").unwrap(),
+            shutdown: Regex::new(
+                r"(?i)Shutting down|Application (?:is )?(?:exiting|quitting)|Changing from Stage \w+ to (?:Exit|Shutdown|Quit)",
+            )
+            .unwrap(),
         }
     }
 }
@@ -75,12 +104,34 @@ impl Default for LogPatterns {
 pub struct LogWatcher {
     patterns: LogPatterns,
     current_log_path: Option<PathBuf>,
+    /// Identity of the file at `current_log_path` when it was opened
+    current_log_id: Option<FileId>,
     file_position: u64,
-    current_state: GameState,
+    machine: StateMachine,
     pending_world_name: Option<String>,
     pending_server_address: Option<String>,
     pending_server_name: Option<String>,
     is_multiplayer: bool,
+    /// The server being joined runs on this machine (e.g. hosted by the client)
+    local_server: bool,
+    session_start: Option<i64>,
+    game_processes: Vec<DetectedProcess>,
+    selection: Option<LogSelection>,
+    /// Log files that were there when `selection` was made
+    log_files: Vec<PathBuf>,
+    /// The game processes changed since `selection` was made
+    processes_changed: bool,
+    /// Timestamp of the log line being parsed
+    line_time: Option<i64>,
+    /// Whether old lines are being replayed while catching up
+    replaying: bool,
+    /// The last lines read from the log, oldest first
+    recent_lines: VecDeque<String>,
+    /// Whether the game logged that it is shutting down
+    shutdown_seen: bool,
+    health: Health,
+    /// Unmatched messages, when capturing them
+    capture: Option<UnknownLines>,
 }
 
 impl LogWatcher {
@@ -89,81 +140,234 @@ impl LogWatcher {
         Self {
             patterns: LogPatterns::new(),
             current_log_path: None,
+            current_log_id: None,
             file_position: 0,
-            current_state: GameState::Unknown,
+            machine: StateMachine::new(),
             pending_world_name: None,
             pending_server_address: None,
             pending_server_name: None,
             is_multiplayer: false,
+            local_server: false,
+            session_start: None,
+            game_processes: Vec::new(),
+            selection: None,
+            log_files: Vec::new(),
+            processes_changed: false,
+            line_time: None,
+            replaying: false,
+            recent_lines: VecDeque::with_capacity(RECENT_LINES),
+            shutdown_seen: false,
+            health: Health::new(),
+            capture: None,
         }
     }
 
     /// Reset the watcher state
     pub fn reset(&mut self) {
         self.current_log_path = None;
+        self.current_log_id = None;
+        self.selection = None;
+        self.log_files.clear();
+        self.forget_log();
+        self.machine.reset();
+        self.session_start = None;
+    }
+
+    /// Forget what was read from the current log, keeping the file
+    fn forget_log(&mut self) {
         self.file_position = 0;
-        self.current_state = GameState::Unknown;
+        self.line_time = None;
         self.pending_world_name = None;
         self.pending_server_address = None;
         self.pending_server_name = None;
         self.is_multiplayer = false;
+        self.local_server = false;
+        self.recent_lines.clear();
+        self.shutdown_seen = false;
+        self.health = Health::new();
+    }
+
+    /// Start reading a log from scratch: forget what was read so far and
+    /// leave the current state. Returns whether the state changed.
+    fn start_over(&mut self) -> bool {
+        self.forget_log();
+        self.set_state(GameState::Unknown, Cause::LogReset)
     }
 
     /// Get current game state
     pub fn state(&self) -> &GameState {
-        &self.current_state
+        self.machine.state()
+    }
+
+    /// Unix timestamp (seconds) at which the current in-game session started
+    pub fn session_start(&self) -> Option<i64> {
+        self.session_start
+    }
+
+    /// Path of the log file being watched
+    pub fn current_log_path(&self) -> Option<&Path> {
+        self.current_log_path.as_deref()
+    }
+
+    /// The last lines read from the log, oldest first
+    pub fn recent_lines(&self) -> Vec<String> {
+        self.recent_lines.iter().cloned().collect()
+    }
+
+    /// Whether the game logged that it is shutting down
+    pub fn shutdown_seen(&self) -> bool {
+        self.shutdown_seen
+    }
+
+    /// Warnings and errors logged during the current session
+    pub fn health(&self) -> &Health {
+        &self.health
+    }
+
+    /// Start collecting messages that no pattern matches
+    pub fn enable_capture(&mut self) {
+        self.capture.get_or_insert_with(UnknownLines::new);
+    }
+
+    /// Messages that no pattern matched, if capturing is enabled
+    pub fn capture(&self) -> Option<&UnknownLines> {
+        self.capture.as_ref()
+    }
+
+    /// Take the transitions observed since the last call
+    pub fn drain_transitions(&mut self) -> Vec<Transition> {
+        self.machine.take()
+    }
+
+    /// Call `hook` with every transition as it is drained
+    pub fn subscribe(&mut self, hook: impl FnMut(&Transition) + Send + 'static) {
+        self.machine.subscribe(hook);
+    }
+
+    /// End the session of a game client that exited
+    pub fn game_exited(&mut self) {
+        self.line_time = None;
+        self.set_state(GameState::Unknown, Cause::GameExited);
+    }
+
+    /// Switch to a new state if the state machine allows it, recording the
+    /// transition. Returns whether the state changed.
+    fn set_state(&mut self, new_state: GameState, cause: Cause) -> bool {
+        if new_state == *self.machine.state() {
+            return false;
+        }
+
+        // Prefer the time of the log line, so sessions that started before
+        // the app did still get the right start time. Replayed lines without
+        // one are only known to be from after the game started.
+        let game_start = self.game_process().map(|p| p.start_time as i64);
+        let at = self
+            .line_time
+            .or(game_start.filter(|_| self.replaying))
+            .unwrap_or_else(unix_now);
+        // Nothing in this session happened before the game process started
+        let at = game_start.map_or(at, |start| at.max(start));
+        let was_in_game = self.machine.state().is_in_game();
+        if !self.machine.apply(new_state, cause, at) {
+            return false;
+        }
+        if !self.machine.state().is_in_game() {
+            self.session_start = None;
+        } else if !was_in_game {
+            self.session_start = Some(at);
+        }
+        true
     }
 
-    /// Find the most recent log file
-    fn find_latest_log_file(&self) -> Option<PathBuf> {
-        let log_dirs = get_log_directories();
-        let mut latest_file: Option<(PathBuf, SystemTime)> = None;
+    /// Tell the watcher which game processes are running, so it can pick
+    /// the log file that belongs to them
+    pub fn set_game_processes(&mut self, processes: Vec<DetectedProcess>) {
+        let same = |a: &DetectedProcess, b: &DetectedProcess| a.pid == b.pid && a.start_time == b.start_time;
+        if processes.len() != self.game_processes.len()
+            || !processes.iter().zip(&self.game_processes).all(|(a, b)| same(a, b))
+        {
+            self.processes_changed = true;
+        }
+        self.game_processes = processes;
+    }
+
+    /// The game process whose log is being watched, or the newest one
+    fn game_process(&self) -> Option<&DetectedProcess> {
+        let pid = self.selection.as_ref().and_then(|s| s.reason.pid());
+        self.game_processes
+            .iter()
+            .find(|p| Some(p.pid) == pid)
+            .or_else(|| self.game_processes.first())
+    }
+
+    /// The log file being watched and why it was chosen
+    pub fn selection(&self) -> Option<&LogSelection> {
+        self.selection.as_ref()
+    }
+
+    /// Pick the log file to watch, see [`LogWatcher::choose_from`]
+    fn choose_log_file(&mut self) -> Option<LogSelection> {
+        self.choose_from(find_log_files())
+    }
 
-        for dir in log_dirs {
-            if !dir.exists() {
-                continue;
+    /// Pick the log file to watch among `log_files`, sticking to the file
+    /// chosen for a game process for as long as that process runs. Other
+    /// selections are kept until the game processes change or a log file
+    /// is added or removed.
+    fn choose_from(&mut self, mut log_files: Vec<PathBuf>) -> Option<LogSelection> {
+        if let Some(ref selection) = self.selection {
+            let pid = selection.reason.pid();
+            let still_running = pid.is_some_and(|pid| self.game_processes.iter().any(|p| p.pid == pid));
+            if still_running && selection.path.exists() {
+                return Some(selection.clone());
             }
+        }
 
-            // Convert glob pattern to regex-like matching
-            let pattern = LOG_FILE_PATTERN.replace("*", "");
-
-            if let Ok(entries) = fs::read_dir(&dir) {
-                for entry in entries.flatten() {
-                    let path = entry.path();
-                    if let Some(filename) = path.file_name().and_then(|n| n.to_str()) {
-                        if filename.ends_with(&pattern) {
-                            if let Ok(metadata) = entry.metadata() {
-                                if let Ok(modified) = metadata.modified() {
-                                    match &latest_file {
-                                        None => latest_file = Some((path, modified)),
-                                        Some((_, latest_time)) if modified > *latest_time => {
-                                            latest_file = Some((path, modified));
-                                        }
-                                        _ => {}
-                                    }
-                                }
-                            }
-                        }
-                    }
-                }
+        log_files.sort();
+        let unchanged = !self.processes_changed && log_files == self.log_files;
+        if let Some(ref selection) = self.selection {
+            if unchanged && selection.path.exists() {
+                return Some(selection.clone());
             }
         }
 
-        latest_file.map(|(path, _)| path)
+        self.processes_changed = false;
+        let selection = select_log_file(&log_files, &self.game_processes);
+        self.log_files = log_files;
+        selection
     }
 
     /// Update the log watcher, reading new lines and updating state
     pub fn update(&mut self) -> Result<bool> {
-        // Find latest log file if we don't have one or it changed
-        let latest_log = self.find_latest_log_file();
+        // Find the log file if we don't have one or it changed
+        let selection = self.choose_log_file();
+        self.update_from(selection)
+    }
+
+    /// Read the log file in `selection`, starting over if it isn't the one
+    /// read so far
+    fn update_from(&mut self, selection: Option<LogSelection>) -> Result<bool> {
+        let latest_log = selection.as_ref().map(|s| s.path.clone());
+        let mut catching_up = false;
+        let mut left_state = false;
 
         if latest_log != self.current_log_path {
-            if let Some(ref path) = latest_log {
-                info!("Found log file: {}", path.display());
+            if let Some(ref selection) = selection {
+                info!(
+                    "Found log file: {} ({})",
+                    selection.path.display(),
+                    selection.reason
+                );
+                events::emit(Event::LogFileSwitched {
+                    path: selection.path.clone(),
+                });
             }
             self.current_log_path = latest_log;
-            self.file_position = 0;
+            // Nothing from the previous log carries over to this one
+            left_state = self.start_over();
+            catching_up = true;
         }
+        self.selection = selection;
 
         let log_path = match &self.current_log_path {
             Some(path) => path.clone(),
@@ -177,63 +381,205 @@ impl LogWatcher {
         let file = File::open(&log_path).context("Failed to open log file")?;
         let metadata = file.metadata()?;
         let file_size = metadata.len();
+        metrics::global().set_log_file_size(file_size);
+
+        // A new file under the same name (rotation) starts a new session
+        if self.detect_replacement(&metadata) && !catching_up {
+            info!("Log file was replaced, starting over");
+            left_state = self.start_over();
+            catching_up = true;
+        }
+
+        if catching_up {
+            let caught_up = self.catch_up(file, file_size, CATCH_UP_MAX_BYTES)?;
+            return Ok(left_state || caught_up);
+        }
 
         // Check if file was truncated (new session)
         if file_size < self.file_position {
             info!("Log file was truncated, resetting position");
-            self.file_position = 0;
-            self.current_state = GameState::Unknown;
+            left_state = self.start_over();
         }
 
         // No new content
         if file_size == self.file_position {
+            return Ok(left_state);
+        }
+
+        Ok(self.read_lines(file)? || left_state)
+    }
+
+    /// Read what was appended to the current log file without looking for
+    /// another one, e.g. the last lines written by a game that just exited
+    pub fn read_remaining(&mut self) -> Result<bool> {
+        let Some(path) = self.current_log_path.clone() else {
+            return Ok(false);
+        };
+        let file = File::open(&path).context("Failed to open log file")?;
+        if file.metadata()?.len() <= self.file_position {
             return Ok(false);
         }
+        self.read_lines(file)
+    }
 
+    /// Read a whole log file from the start, recording every transition
+    /// instead of catching up, e.g. to check the patterns against a saved log
+    pub fn replay(&mut self, path: &Path) -> Result<bool> {
+        let file = File::open(path).context("Failed to open log file")?;
+        self.current_log_path = Some(path.to_path_buf());
+        self.file_position = 0;
+        self.read_lines(file)
+    }
+
+    /// Remember the identity of the current file. Returns true if it differs
+    /// from the file that was open before.
+    fn detect_replacement(&mut self, metadata: &Metadata) -> bool {
+        let id = file_id(metadata);
+        let replaced = self.current_log_id.is_some() && id != self.current_log_id;
+        self.current_log_id = id;
+        replaced
+    }
+
+    /// Read and parse all complete lines from the current position.
+    ///
+    /// A trailing line without a newline is still being written, so it is left
+    /// unread until the next call. Invalid UTF-8 is replaced rather than
+    /// failing the whole read.
+    fn read_lines(&mut self, file: File) -> Result<bool> {
         let mut reader = BufReader::new(file);
         reader.seek(SeekFrom::Start(self.file_position))?;
 
         let mut state_changed = false;
-        let mut line = String::new();
+        let mut line = Vec::new();
 
-        while reader.read_line(&mut line)? > 0 {
-            if self.parse_line(&line) {
+        loop {
+            line.clear();
+            let read = reader.read_until(b'\n', &mut line)?;
+            if read == 0 || line.last() != Some(&b'\n') {
+                break;
+            }
+            self.file_position += read as u64;
+
+            metrics::global().inc_log_lines_parsed();
+            let text = String::from_utf8_lossy(&line);
+            if self.recent_lines.len() == RECENT_LINES {
+                self.recent_lines.pop_front();
+            }
+            self.recent_lines.push_back(text.trim_end().to_string());
+            if self.parse_line(&text) {
                 state_changed = true;
             }
-            line.clear();
         }
 
-        self.file_position = reader.stream_position()?;
-
         Ok(state_changed)
     }
 
+    /// Rebuild the state of a freshly opened log without parsing all of it.
+    ///
+    /// Replays the log from the last line that starts over everything
+    /// pending (entering the main menu, or joining a world or server) within
+    /// the last `max_bytes`, and records the outcome as a single transition
+    /// instead of the replayed history.
+    fn catch_up(&mut self, mut file: File, len: u64, max_bytes: u64) -> Result<bool> {
+        let start = self.find_catch_up_start(&mut file, len, max_bytes)?;
+        if start > 0 {
+            info!("Catching up from byte {} of {}", start, len);
+        }
+
+        let mark = self.machine.mark();
+        self.file_position = start;
+        self.replaying = true;
+        let result = self.read_lines(file);
+        self.replaying = false;
+        result?;
+
+        Ok(self.machine.squash(mark))
+    }
+
+    /// Scan backward from the end of the file for the last main menu, world
+    /// join or server join line. Falls back to the first complete line within
+    /// `max_bytes` of the end.
+    fn find_catch_up_start(&self, file: &mut File, len: u64, max_bytes: u64) -> Result<u64> {
+        let floor = len.saturating_sub(max_bytes);
+        let mut chunk_end = len;
+        let mut fallback = floor;
+        // Start of the line that straddles the previous chunk boundary
+        let mut carry: Vec<u8> = Vec::new();
+
+        while chunk_end > floor {
+            let chunk_start = chunk_end.saturating_sub(CATCH_UP_CHUNK_BYTES).max(floor);
+            let mut buf = vec![0; (chunk_end - chunk_start) as usize];
+            file.seek(SeekFrom::Start(chunk_start))?;
+            file.read_exact(&mut buf)?;
+            buf.extend_from_slice(&carry);
+
+            // Unless this is the start of the file, the first line may be cut off
+            let first_line = if chunk_start == 0 {
+                0
+            } else {
+                buf.iter()
+                    .position(|&b| b == b'\n')
+                    .map_or(buf.len(), |i| i + 1)
+            };
+            fallback = chunk_start + first_line as u64;
+
+            let mut lines = Vec::new();
+            let mut offset = first_line;
+            for line in buf[first_line..].split(|&b| b == b'\n') {
+                lines.push((offset, line));
+                offset += line.len() + 1;
+            }
+            for (offset, line) in lines.into_iter().rev() {
+                let is_anchor = LogEntry::parse(&String::from_utf8_lossy(line))
+                    .is_some_and(|entry| self.is_catch_up_anchor(&entry.message));
+                if is_anchor {
+                    return Ok(chunk_start + offset as u64);
+                }
+            }
+
+            carry = buf[..first_line].to_vec();
+            chunk_end = chunk_start;
+        }
+
+        Ok(fallback)
+    }
+
+    /// Whether a session can be rebuilt from this line on: the main menu
+    /// and world or server joins come before the names and addresses that
+    /// the following lines fill in
+    fn is_catch_up_anchor(&self, message: &str) -> bool {
+        let patterns = &self.patterns;
+        patterns.main_menu.is_match(message)
+            || patterns.singleplayer_world.is_match(message)
+            || patterns.singleplayer_create.is_match(message)
+            || patterns.multiplayer_connect.is_match(message)
+    }
+
     /// Parse a single log line and update state
     fn parse_line(&mut self, raw_line: &str) -> bool {
-        let raw_line = raw_line.trim();
-        if raw_line.is_empty() {
-            return false;
+        match LogEntry::parse(raw_line) {
+            Some(entry) => self.parse_entry(&entry),
+            None => false,
         }
+    }
 
-        // Try to extract message from pipe-delimited format
-        // Format: Timestamp|Level|Source|Message
-        // If it matches this format, use the message part. Otherwise use the whole line.
-        let parts: Vec<&str> = raw_line.splitn(4, '|').collect();
-        let line = if parts.len() == 4 {
-            parts[3].trim()
-        } else {
-            raw_line
-        };
+    /// Update state from a parsed log entry. Returns whether the state
+    /// machine took a transition.
+    fn parse_entry(&mut self, entry: &LogEntry) -> bool {
+        self.line_time = entry.unix_timestamp();
+        self.health.record(entry);
+        let line = entry.message.as_str();
 
         // Check for main menu
         if self.patterns.main_menu.is_match(line) {
             debug!("Detected: Main Menu");
-            self.current_state = GameState::MainMenu;
+            let changed = self.set_state(GameState::MainMenu, Cause::MainMenu);
             self.pending_world_name = None;
             self.pending_server_address = None;
             self.pending_server_name = None;
             self.is_multiplayer = false;
-            return true;
+            self.local_server = false;
+            return changed;
         }
 
         // Check for singleplayer world connection
@@ -243,12 +589,14 @@ impl LogWatcher {
                 debug!("Detected: Connecting to singleplayer world '{}'", name);
                 self.pending_world_name = Some(name.clone());
                 self.is_multiplayer = false;
-                self.current_state = GameState::Loading {
-                    world_name: Some(name),
-                    is_multiplayer: false,
-                    sub_stage: None,
-                };
-                return true;
+                return self.set_state(
+                    GameState::Loading {
+                        world_name: Some(name),
+                        is_multiplayer: false,
+                        sub_stage: None,
+                    },
+                    Cause::JoinWorld,
+                );
             }
         }
 
@@ -256,24 +604,36 @@ impl LogWatcher {
         if self.patterns.singleplayer_create.is_match(line) {
             debug!("Detected: Creating singleplayer world");
             self.is_multiplayer = false;
-            self.current_state = GameState::Loading {
-                world_name: self.pending_world_name.clone(),
-                is_multiplayer: false,
-                sub_stage: None,
-            };
-            return true;
+            return self.set_state(
+                GameState::Loading {
+                    world_name: self.pending_world_name.clone(),
+                    is_multiplayer: false,
+                    sub_stage: None,
+                },
+                Cause::JoinWorld,
+            );
         }
 
         // Check for multiplayer connection
-        if self.patterns.multiplayer_connect.is_match(line) {
+        if self.patterns.multiplayer_connect.is_match(line)
+            || self.patterns.server_established.is_match(line)
+        {
+            // "Server connection established" follows the address, which
+            // may have shown that the server is local
+            if self.local_server {
+                debug!("Detected: Local server connection");
+                return false;
+            }
             debug!("Detected: Multiplayer connection");
             self.is_multiplayer = true;
-            self.current_state = GameState::Loading {
-                world_name: None,
-                is_multiplayer: true,
-                sub_stage: None,
-            };
-            return true;
+            return self.set_state(
+                GameState::Loading {
+                    world_name: None,
+                    is_multiplayer: true,
+                    sub_stage: None,
+                },
+                Cause::JoinServer,
+            );
         }
 
         // Check for loading stages
@@ -283,16 +643,19 @@ impl LogWatcher {
                 debug!("Detected: Loading stage '{}'", stage_name);
                 
                 // Only update if we are already in loading state or about to be
-                if let GameState::Loading { world_name, is_multiplayer, .. } = &self.current_state {
+                if let GameState::Loading { world_name, is_multiplayer, .. } = self.machine.state().clone() {
                     // Convert CamelCase to Spaced String (e.g. BootingServer -> Booting Server)
                     let formatted_stage = self.format_stage_name(stage_name);
-                    self.current_state = GameState::Loading {
-                        world_name: world_name.clone(),
-                        is_multiplayer: *is_multiplayer,
-                        sub_stage: Some(format!("Loading: {}", formatted_stage)),
-                    };
-                    return true;
+                    return self.set_state(
+                        GameState::Loading {
+                            world_name,
+                            is_multiplayer,
+                            sub_stage: Some(format!("Loading: {}", formatted_stage)),
+                        },
+                        Cause::LoadingStage,
+                    );
                 }
+                return false;
             }
         }
 
@@ -311,9 +674,11 @@ impl LogWatcher {
                 if is_localhost {
                     debug!("Localhost detected, treating as singleplayer");
                     self.is_multiplayer = false;
+                    self.local_server = true;
                 } else {
                     self.pending_server_address = Some(address);
                     self.is_multiplayer = true;
+                    self.local_server = false;
                 }
                 return false; // Don't trigger state change yet
             }
@@ -335,20 +700,21 @@ impl LogWatcher {
         // Check for in-game transition
         if self.patterns.in_game.is_match(line) || self.patterns.world_loaded.is_match(line) {
             debug!("Detected: In-game / World loaded");
-            if self.is_multiplayer {
-                self.current_state = GameState::Multiplayer {
-                    server_address: self.pending_server_address.clone(),
+            let state = if self.is_multiplayer {
+                GameState::Multiplayer {
+                    server_address: self.pending_server_address(),
                     server_name: self.pending_server_name.clone(),
-                };
+                }
             } else {
-                self.current_state = GameState::Singleplayer {
+                GameState::Singleplayer {
                     world_name: self
                         .pending_world_name
                         .clone()
+                        .or_else(|| self.game_process()?.world_arg().map(str::to_string))
                         .unwrap_or_else(|| "Exploring Orbis".to_string()),
-                };
-            }
-            return true;
+                }
+            };
+            return self.set_state(state, Cause::InGame);
         }
 
         // Check for playing singleplayer indicators
@@ -356,26 +722,44 @@ impl LogWatcher {
             if let Some(world_name) = caps.get(1) {
                 let name = world_name.as_str().to_string();
                 debug!("Detected: Playing singleplayer '{}'", name);
-                self.current_state = GameState::Singleplayer { world_name: name };
-                return true;
+                return self.set_state(GameState::Singleplayer { world_name: name }, Cause::Playing);
             }
+            return false;
         }
 
         // Check for playing multiplayer indicators
         if self.patterns.playing_multiplayer.is_match(line) {
             debug!("Detected: Playing multiplayer");
-            if !matches!(self.current_state, GameState::Multiplayer { .. }) {
-                self.current_state = GameState::Multiplayer {
-                    server_address: self.pending_server_address.clone(),
+            if !matches!(self.machine.state(), GameState::Multiplayer { .. }) {
+                let state = GameState::Multiplayer {
+                    server_address: self.pending_server_address(),
                     server_name: self.pending_server_name.clone(),
                 };
-                return true;
+                return self.set_state(state, Cause::Playing);
             }
+            return false;
+        }
+
+        if self.patterns.shutdown.is_match(line) {
+            debug!("Detected: Shutdown");
+            self.shutdown_seen = true;
+            return false;
         }
 
+        metrics::global().inc_log_lines_unmatched();
+        if let Some(ref mut capture) = self.capture {
+            capture.record(entry);
+        }
         false
     }
 
+    /// Server address from the log, or from the game's `--server` argument
+    fn pending_server_address(&self) -> Option<String> {
+        self.pending_server_address
+            .clone()
+            .or_else(|| self.game_process()?.server_arg().map(str::to_string))
+    }
+
     /// Helper to format stage names (e.g. "BootingServer" -> "Booting Server")
     fn format_stage_name(&self, stage: &str) -> String {
         let mut result = String::new();
@@ -395,9 +779,182 @@ impl Default for LogWatcher {
     }
 }
 
+/// Why a log file was chosen
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SelectionReason {
+    /// The game process has the file open
+    OpenByGame { pid: u32 },
+    /// The file was created shortly after the game process started
+    CreatedAtGameStart { pid: u32, seconds_after: u64 },
+    /// No running game could be matched, so the newest log is used
+    Newest,
+}
+
+impl SelectionReason {
+    /// The game process the file was matched with
+    pub fn pid(&self) -> Option<u32> {
+        match self {
+            SelectionReason::OpenByGame { pid } | SelectionReason::CreatedAtGameStart { pid, .. } => {
+                Some(*pid)
+            }
+            SelectionReason::Newest => None,
+        }
+    }
+}
+
+impl fmt::Display for SelectionReason {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SelectionReason::OpenByGame { pid } => write!(f, "opened by game process {}", pid),
+            SelectionReason::CreatedAtGameStart { pid, seconds_after } => write!(
+                f,
+                "created {}s after game process {} started",
+                seconds_after, pid
+            ),
+            SelectionReason::Newest => write!(f, "most recently modified log"),
+        }
+    }
+}
+
+/// A log file together with the reason it was chosen
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LogSelection {
+    pub path: PathBuf,
+    pub reason: SelectionReason,
+}
+
+/// All client log files in the known log directories
+pub fn find_log_files() -> Vec<PathBuf> {
+    // Convert glob pattern to suffix matching
+    let suffix = LOG_FILE_PATTERN.replace('*', "");
+    let mut files = Vec::new();
+
+    for dir in get_log_directories() {
+        let Ok(entries) = fs::read_dir(&dir) else {
+            continue;
+        };
+        for entry in entries.flatten() {
+            let path = entry.path();
+            let is_log = path
+                .file_name()
+                .and_then(|n| n.to_str())
+                .is_some_and(|n| n.ends_with(&suffix));
+            if is_log && path.is_file() {
+                files.push(path);
+            }
+        }
+    }
+
+    files
+}
+
+/// Pick the log file belonging to one of the running game processes
+/// (most recently started first), falling back to the newest log
+pub fn select_log_file(candidates: &[PathBuf], processes: &[DetectedProcess]) -> Option<LogSelection> {
+    for process in processes {
+        let open_files = open_files(process.pid);
+        if let Some(path) = candidates.iter().find(|path| {
+            fs::canonicalize(path).is_ok_and(|path| open_files.contains(&path))
+        }) {
+            return Some(LogSelection {
+                path: path.clone(),
+                reason: SelectionReason::OpenByGame { pid: process.pid },
+            });
+        }
+    }
+
+    for process in processes {
+        let closest = candidates
+            .iter()
+            .filter_map(|path| {
+                let created = fs::metadata(path).ok()?.created().ok()?;
+                let created = created.duration_since(UNIX_EPOCH).ok()?.as_secs();
+                let after = created.checked_sub(process.start_time.saturating_sub(CREATED_SLACK_SECS))?;
+                let seconds_after = after.saturating_sub(CREATED_SLACK_SECS);
+                (after <= CREATED_WINDOW_SECS + CREATED_SLACK_SECS).then_some((path, seconds_after))
+            })
+            .min_by_key(|(_, seconds_after)| *seconds_after);
+        if let Some((path, seconds_after)) = closest {
+            return Some(LogSelection {
+                path: path.clone(),
+                reason: SelectionReason::CreatedAtGameStart {
+                    pid: process.pid,
+                    seconds_after,
+                },
+            });
+        }
+    }
+
+    candidates
+        .iter()
+        .filter_map(|path| Some((path, fs::metadata(path).ok()?.modified().ok()?)))
+        .max_by_key(|(_, modified)| *modified)
+        .map(|(path, _)| LogSelection {
+            path: path.clone(),
+            reason: SelectionReason::Newest,
+        })
+}
+
+/// Files the process has open (only available on Linux)
+#[cfg(target_os = "linux")]
+fn open_files(pid: u32) -> Vec<PathBuf> {
+    fs::read_dir(format!("/proc/{}/fd", pid))
+        .map(|entries| {
+            entries
+                .flatten()
+                .filter_map(|entry| fs::read_link(entry.path()).ok())
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+#[cfg(not(target_os = "linux"))]
+fn open_files(_pid: u32) -> Vec<PathBuf> {
+    Vec::new()
+}
+
+/// Identity of a file on disk, stable while the file exists
+type FileId = (u64, u64);
+
+#[cfg(unix)]
+fn file_id(metadata: &Metadata) -> Option<FileId> {
+    use std::os::unix::fs::MetadataExt;
+    Some((metadata.dev(), metadata.ino()))
+}
+
+/// Without stable access to file IDs, fall back to the creation time
+#[cfg(not(unix))]
+fn file_id(metadata: &Metadata) -> Option<FileId> {
+    let created = metadata.created().ok()?.duration_since(UNIX_EPOCH).ok()?;
+    Some((created.as_secs(), created.subsec_nanos() as u64))
+}
+
+/// Current time as a Unix timestamp in seconds
+pub(crate) fn unix_now() -> i64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs() as i64)
+        .unwrap_or(0)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::process::Runtime;
+    use std::io::Write;
+    use std::time::Duration;
+
+    fn process(pid: u32, start_time: u64) -> DetectedProcess {
+        DetectedProcess {
+            pid,
+            name: "HytaleClient".to_string(),
+            exe: None,
+            cmdline: Vec::new(),
+            start_time,
+            parent: None,
+            runtime: Runtime::Native,
+        }
+    }
 
     #[test]
     fn test_log_patterns() {
@@ -434,6 +991,44 @@ mod tests {
         assert!(matches!(watcher.state(), GameState::MainMenu));
     }
 
+    #[test]
+    fn test_session_start_uses_log_timestamp() {
+        let mut watcher = LogWatcher::new();
+        watcher.parse_line(r#"2026-01-25 11:16:40.2349|INFO|HytaleClient.Application.AppStartup|Connecting to singleplayer world "TestWorld""#);
+        watcher.parse_line("2026-01-25 11:17:02.0001|INFO|HytaleClient.Application.AppStartup|Changing from Stage GameLoading to InGame");
+
+        let expected = LogEntry::parse("2026-01-25 11:17:02.0001|INFO|X|y")
+            .and_then(|e| e.unix_timestamp())
+            .unwrap();
+        assert!(matches!(watcher.state(), GameState::Singleplayer { .. }));
+        assert_eq!(watcher.session_start(), Some(expected));
+        assert_eq!(watcher.drain_transitions().last().unwrap().at, expected);
+    }
+
+    #[test]
+    fn test_game_process_start_and_args() {
+        let game_start = LogEntry::parse("2026-01-25 11:17:00.0000|INFO|X|y")
+            .and_then(|e| e.unix_timestamp())
+            .unwrap();
+        let mut game = process(1, game_start as u64);
+        game.cmdline = vec!["--server".to_string(), "play.example.com:5520".to_string()];
+
+        let mut watcher = LogWatcher::new();
+        watcher.set_game_processes(vec![game]);
+        // A stale line from before the game started
+        watcher.parse_line("2026-01-25 11:10:00.0000|INFO|App|Connecting to multiplayer server");
+        watcher.parse_line("2026-01-25 11:10:05.0000|INFO|App|Changing from Stage GameLoading to InGame");
+
+        assert_eq!(
+            *watcher.state(),
+            GameState::Multiplayer {
+                server_address: Some("play.example.com:5520".to_string()),
+                server_name: None,
+            }
+        );
+        assert_eq!(watcher.session_start(), Some(game_start));
+    }
+
     #[test]
     fn test_loading_stages() {
         let mut watcher = LogWatcher::new();
@@ -459,4 +1054,338 @@ mod tests {
             panic!("State should be Loading");
         }
     }
+
+    #[test]
+    fn test_stray_lines_are_rejected() {
+        let mut watcher = LogWatcher::new();
+        assert!(watcher.parse_line("2026-01-25 11:00:00.0000|INFO|App|Changing from Stage Startup to MainMenu"));
+        assert!(!watcher.parse_line("2026-01-25 11:00:05.0000|INFO|App|World loaded"));
+        assert!(!watcher.parse_line("2026-01-25 11:00:06.0000|INFO|App|Changing from loading stage Initial to BootingServer"));
+        assert_eq!(*watcher.state(), GameState::MainMenu);
+        assert_eq!(watcher.session_start(), None);
+
+        let causes: Vec<Cause> = watcher.drain_transitions().iter().map(|t| t.cause).collect();
+        assert_eq!(causes, [Cause::MainMenu]);
+    }
+
+    fn log_line(time: &str, message: &str) -> String {
+        format!("2026-01-25 {}|INFO|HytaleClient.Application.AppStartup|{}\n", time, message)
+    }
+
+    #[test]
+    fn test_catch_up_mid_session() {
+        let mut log = String::new();
+        log.push_str(&log_line("10:00:00.0000", r#"Connecting to singleplayer world "Old""#));
+        log.push_str(&log_line("10:00:05.0000", "Changing from Stage GameLoading to InGame"));
+        log.push_str(&log_line("11:00:00.0000", "Changing from Stage Loading to MainMenu"));
+        let join_offset = log.len();
+        log.push_str(&log_line("11:16:40.0000", r#"Connecting to singleplayer world "Orbis""#));
+        log.push_str(&log_line("11:17:02.0000", "Changing from Stage GameLoading to InGame"));
+        for i in 0..5000 {
+            log.push_str(&log_line("11:30:00.0000", &format!("Chunk {} loaded", i)));
+        }
+
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("test_client.log");
+        fs::write(&path, &log).unwrap();
+
+        let mut watcher = LogWatcher::new();
+        let mut file = File::open(&path).unwrap();
+        let len = log.len() as u64;
+        assert_eq!(
+            watcher.find_catch_up_start(&mut file, len, len).unwrap(),
+            join_offset as u64
+        );
+
+        assert!(watcher.catch_up(file, len, len).unwrap());
+        assert_eq!(
+            *watcher.state(),
+            GameState::Singleplayer {
+                world_name: "Orbis".to_string()
+            }
+        );
+        let in_game_at = LogEntry::parse(&log_line("11:17:02.0000", "x"))
+            .and_then(|e| e.unix_timestamp())
+            .unwrap();
+        assert_eq!(watcher.session_start(), Some(in_game_at));
+
+        let transitions = watcher.drain_transitions();
+        assert_eq!(transitions.len(), 1);
+        assert_eq!(transitions[0].from, GameState::Unknown);
+        assert_eq!(transitions[0].at, in_game_at);
+        assert_eq!(watcher.file_position, len);
+    }
+
+    #[test]
+    fn test_catch_up_without_main_menu() {
+        // Only the end of a long session: no main menu line in the window
+        let mut log = String::new();
+        for i in 0..100 {
+            log.push_str(&log_line("10:00:00.0000", &format!("Chunk {} loaded", i)));
+        }
+        let join_offset = log.len();
+        log.push_str(&log_line("11:16:40.0000", "Connecting to multiplayer server"));
+        log.push_str(&log_line("11:16:41.0000", "Opening Quic Connection to play.example.net:5520"));
+        log.push_str(&log_line("11:16:42.0000", "Server connection established"));
+        log.push_str(&log_line("11:17:02.0000", "Changing from Stage GameLoading to InGame"));
+        for i in 0..1000 {
+            log.push_str(&log_line("11:30:00.0000", &format!("Chunk {} loaded", i)));
+        }
+
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("test_client.log");
+        fs::write(&path, &log).unwrap();
+
+        let mut watcher = LogWatcher::new();
+        let mut file = File::open(&path).unwrap();
+        let len = log.len() as u64;
+        assert_eq!(
+            watcher.find_catch_up_start(&mut file, len, len).unwrap(),
+            join_offset as u64
+        );
+
+        assert!(watcher.catch_up(file, len, len).unwrap());
+        assert_eq!(
+            *watcher.state(),
+            GameState::Multiplayer {
+                server_address: Some("play.example.net:5520".to_string()),
+                server_name: None,
+            }
+        );
+        let in_game_at = LogEntry::parse(&log_line("11:17:02.0000", "x"))
+            .and_then(|e| e.unix_timestamp())
+            .unwrap();
+        assert_eq!(watcher.session_start(), Some(in_game_at));
+    }
+
+    #[test]
+    fn test_catch_up_is_bounded() {
+        let mut log = log_line("11:00:00.0000", "Changing from Stage Loading to MainMenu");
+        for i in 0..100 {
+            log.push_str(&log_line("11:30:00.0000", &format!("Chunk {} loaded", i)));
+        }
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("test_client.log");
+        fs::write(&path, &log).unwrap();
+
+        // The main menu line is outside the window, so the scan stops at the
+        // first complete line inside it
+        let watcher = LogWatcher::new();
+        let len = log.len() as u64;
+        let start = watcher
+            .find_catch_up_start(&mut File::open(&path).unwrap(), len, 1000)
+            .unwrap();
+        assert!(start >= len - 1000);
+        assert_eq!(log.as_bytes()[start as usize - 1], b'\n');
+    }
+
+    #[test]
+    fn test_partial_lines_and_invalid_utf8() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("test_client.log");
+
+        let mut bytes = b"2026-01-25 11:00:00.0000|INFO|Chat|caf\xe9\n".to_vec();
+        let complete = bytes.len() as u64;
+        bytes.extend_from_slice(b"2026-01-25 11:00:01.0000|INFO|App|Changing from Stage Loading to Main");
+        fs::write(&path, &bytes).unwrap();
+
+        let mut watcher = LogWatcher::new();
+        assert!(!watcher.read_lines(File::open(&path).unwrap()).unwrap());
+        assert_eq!(watcher.file_position, complete);
+        assert_eq!(*watcher.state(), GameState::Unknown);
+
+        bytes.extend_from_slice(b"Menu\n");
+        fs::write(&path, &bytes).unwrap();
+        assert!(watcher.read_lines(File::open(&path).unwrap()).unwrap());
+        assert_eq!(*watcher.state(), GameState::MainMenu);
+        assert_eq!(watcher.file_position, bytes.len() as u64);
+    }
+
+    #[test]
+    fn test_recent_lines_and_shutdown() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("test_client.log");
+        fs::write(&path, "2026-01-25 11:00:00.0000|INFO|App|Changing from Stage Startup to MainMenu\n").unwrap();
+
+        let mut watcher = LogWatcher::new();
+        watcher.current_log_path = Some(path.clone());
+        assert!(watcher.read_remaining().unwrap());
+        assert!(!watcher.read_remaining().unwrap());
+        assert!(!watcher.shutdown_seen());
+
+        let mut file = File::options().append(true).open(&path).unwrap();
+        for i in 0..RECENT_LINES {
+            writeln!(file, "2026-01-25 11:00:01.0000|DEBUG|App|line {}", i).unwrap();
+        }
+        writeln!(file, "2026-01-25 11:00:02.0000|INFO|App|Shutting down").unwrap();
+        watcher.read_remaining().unwrap();
+
+        let recent = watcher.recent_lines();
+        assert_eq!(recent.len(), RECENT_LINES);
+        assert_eq!(recent[0], "2026-01-25 11:00:01.0000|DEBUG|App|line 1");
+        assert!(watcher.shutdown_seen());
+
+        watcher.reset();
+        assert!(watcher.recent_lines().is_empty());
+        assert!(!watcher.shutdown_seen());
+    }
+
+    #[test]
+    fn test_detect_replacement() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("test_client.log");
+        fs::write(&path, "old\n").unwrap();
+        // Keep the old file open so its identity can't be reused
+        let old = File::open(&path).unwrap();
+
+        let mut watcher = LogWatcher::new();
+        assert!(!watcher.detect_replacement(&old.metadata().unwrap()));
+        assert!(!watcher.detect_replacement(&fs::metadata(&path).unwrap()));
+
+        let new_path = dir.path().join("new_client.log");
+        fs::write(&new_path, "new\n").unwrap();
+        fs::rename(&new_path, &path).unwrap();
+        assert!(watcher.detect_replacement(&fs::metadata(&path).unwrap()));
+    }
+
+    #[test]
+    fn test_select_log_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let older = dir.path().join("older_client.log");
+        let newer = dir.path().join("newer_client.log");
+        fs::write(&older, "a\n").unwrap();
+        fs::write(&newer, "b\n").unwrap();
+        let old_time = SystemTime::now() - Duration::from_secs(3600);
+        File::options()
+            .write(true)
+            .open(&older)
+            .unwrap()
+            .set_modified(old_time)
+            .unwrap();
+        let candidates = vec![older.clone(), newer.clone()];
+
+        // No game process: newest wins
+        let selection = select_log_file(&candidates, &[]).unwrap();
+        assert_eq!(selection.path, newer);
+        assert_eq!(selection.reason, SelectionReason::Newest);
+
+        // A game process that started long after both files were created
+        let late = process(u32::MAX, unix_now() as u64 + 3600);
+        let selection = select_log_file(&candidates, std::slice::from_ref(&late)).unwrap();
+        assert_eq!(selection.reason, SelectionReason::Newest);
+
+        // A game process that started just before the files were created
+        let fresh = process(u32::MAX, unix_now() as u64 - 1);
+        if fs::metadata(&older).unwrap().created().is_ok() {
+            let selection = select_log_file(&candidates, std::slice::from_ref(&fresh)).unwrap();
+            assert!(matches!(
+                selection.reason,
+                SelectionReason::CreatedAtGameStart { pid: u32::MAX, .. }
+            ));
+        }
+
+        // A game process holding the older file open
+        #[cfg(target_os = "linux")]
+        {
+            let _open = File::open(&older).unwrap();
+            let this = process(std::process::id(), 0);
+            let selection = select_log_file(&candidates, &[fresh, this]).unwrap();
+            assert_eq!(selection.path, older);
+            assert_eq!(
+                selection.reason,
+                SelectionReason::OpenByGame {
+                    pid: std::process::id()
+                }
+            );
+        }
+    }
+
+    #[test]
+    fn test_switch_log_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let first = dir.path().join("first_client.log");
+        let second = dir.path().join("second_client.log");
+        let mut log = String::new();
+        log.push_str(&log_line("10:00:00.0000", "Opening Quic Connection to play.example.net:5520"));
+        log.push_str(&log_line("10:00:01.0000", r#"Server name: "Example SMP""#));
+        log.push_str(&log_line("10:00:02.0000", "Connecting to multiplayer server"));
+        log.push_str(&log_line("10:00:05.0000", "Changing from Stage GameLoading to InGame"));
+        fs::write(&first, log).unwrap();
+        // The other instance is still loading, with nothing named yet
+        fs::write(
+            &second,
+            log_line("11:00:00.0000", "Changing from loading stage Initial to BootingServer"),
+        )
+        .unwrap();
+        let select = |path: &Path| {
+            Some(LogSelection {
+                path: path.to_path_buf(),
+                reason: SelectionReason::Newest,
+            })
+        };
+
+        let mut watcher = LogWatcher::new();
+        assert!(watcher.update_from(select(&first)).unwrap());
+        assert!(matches!(watcher.state(), GameState::Multiplayer { .. }));
+        watcher.drain_transitions();
+
+        // Nothing of the first log's session leaks into the second
+        assert!(watcher.update_from(select(&second)).unwrap());
+        assert_eq!(*watcher.state(), GameState::Unknown);
+        assert_eq!(watcher.session_start(), None);
+        let causes: Vec<Cause> = watcher.drain_transitions().iter().map(|t| t.cause).collect();
+        assert_eq!(causes, [Cause::LogReset]);
+
+        fs::OpenOptions::new()
+            .append(true)
+            .open(&second)
+            .unwrap()
+            .write_all(log_line("11:00:05.0000", "Changing from Stage GameLoading to InGame").as_bytes())
+            .unwrap();
+        assert!(watcher.update_from(select(&second)).unwrap());
+        assert_eq!(
+            *watcher.state(),
+            GameState::Singleplayer {
+                world_name: "Exploring Orbis".to_string()
+            }
+        );
+    }
+
+    #[test]
+    fn test_selection_cached() {
+        let dir = tempfile::tempdir().unwrap();
+        let older = dir.path().join("older_client.log");
+        let newer = dir.path().join("newer_client.log");
+        fs::write(&older, "a\n").unwrap();
+        fs::write(&newer, "b\n").unwrap();
+        let set_modified = |path: &Path, ago: u64| {
+            File::options()
+                .write(true)
+                .open(path)
+                .unwrap()
+                .set_modified(SystemTime::now() - Duration::from_secs(ago))
+                .unwrap();
+        };
+        set_modified(&older, 3600);
+
+        let mut watcher = LogWatcher::new();
+        watcher.selection = watcher.choose_from(vec![older.clone(), newer.clone()]);
+        assert_eq!(watcher.selection.as_ref().unwrap().path, newer);
+
+        // Writing to another log doesn't move the selection by itself
+        set_modified(&newer, 7200);
+        watcher.selection = watcher.choose_from(vec![newer.clone(), older.clone()]);
+        assert_eq!(watcher.selection.as_ref().unwrap().path, newer);
+
+        // A new game process does
+        watcher.set_game_processes(vec![process(u32::MAX, unix_now() as u64 + 3600)]);
+        watcher.selection = watcher.choose_from(vec![older.clone(), newer.clone()]);
+        assert_eq!(watcher.selection.as_ref().unwrap().path, older);
+
+        // So does a new log file
+        let latest = dir.path().join("latest_client.log");
+        fs::write(&latest, "c\n").unwrap();
+        watcher.selection = watcher.choose_from(vec![older, newer, latest.clone()]);
+        assert_eq!(watcher.selection.as_ref().unwrap().path, latest);
+    }
 }
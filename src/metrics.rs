@@ -0,0 +1,304 @@
+//! Prometheus metrics for playtime and app health
+//!
+//! Counters live in a process-wide [`Metrics`] instance that the log watcher,
+//! the Discord output and the main loop update as they go. Time per state
+//! follows the state machine's transitions (and the launcher coming and
+//! going). The HTTP server renders them in the Prometheus text format on
+//! `GET /metrics`.
+
+use std::fmt::Write;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use crate::resources::ResourceUsage;
+
+/// Every value of [`GameState::kind`](crate::config::GameState::kind)
+const STATE_KINDS: [&str; 6] = [
+    "unknown",
+    "launcher",
+    "main_menu",
+    "loading",
+    "singleplayer",
+    "multiplayer",
+];
+
+static METRICS: Metrics = Metrics::new();
+
+/// Get the process-wide metrics
+pub fn global() -> &'static Metrics {
+    &METRICS
+}
+
+/// Counters and gauges exposed on `/metrics`
+pub struct Metrics {
+    state_micros: [AtomicU64; STATE_KINDS.len()],
+    /// Index of the state being counted and when it was entered
+    current_state: Mutex<Option<(usize, Instant)>>,
+    discord_reconnects: AtomicU64,
+    rpc_update_failures: AtomicU64,
+    log_lines_parsed: AtomicU64,
+    log_lines_unmatched: AtomicU64,
+    transitions_rejected: AtomicU64,
+    log_file_size: AtomicU64,
+    poll_loop_micros: AtomicU64,
+    poll_loop_micros_sum: AtomicU64,
+    poll_loop_count: AtomicU64,
+    /// Whether the game resource gauges below hold a current sample
+    game_resources: AtomicBool,
+    game_cpu_percent_bits: AtomicU64,
+    game_memory_bytes: AtomicU64,
+    game_uptime_secs: AtomicU64,
+}
+
+impl Metrics {
+    /// Create a set of zeroed metrics
+    pub const fn new() -> Self {
+        Self {
+            state_micros: [const { AtomicU64::new(0) }; STATE_KINDS.len()],
+            current_state: Mutex::new(None),
+            discord_reconnects: AtomicU64::new(0),
+            rpc_update_failures: AtomicU64::new(0),
+            log_lines_parsed: AtomicU64::new(0),
+            log_lines_unmatched: AtomicU64::new(0),
+            transitions_rejected: AtomicU64::new(0),
+            log_file_size: AtomicU64::new(0),
+            poll_loop_micros: AtomicU64::new(0),
+            poll_loop_micros_sum: AtomicU64::new(0),
+            poll_loop_count: AtomicU64::new(0),
+            game_resources: AtomicBool::new(false),
+            game_cpu_percent_bits: AtomicU64::new(0),
+            game_memory_bytes: AtomicU64::new(0),
+            game_uptime_secs: AtomicU64::new(0),
+        }
+    }
+
+    /// Count time for the state of the given kind from `at` on, adding the
+    /// time spent in the previous one
+    pub fn enter_state(&self, kind: &str, at: Instant) {
+        let mut current = self.current_state.lock().unwrap();
+        if let Some((index, since)) = *current {
+            let elapsed = at.saturating_duration_since(since);
+            self.state_micros[index].fetch_add(elapsed.as_micros() as u64, Ordering::Relaxed);
+        }
+        *current = STATE_KINDS.iter().position(|k| *k == kind).map(|index| (index, at));
+    }
+
+    #[cfg(feature = "discord")]
+    pub fn inc_discord_reconnects(&self) {
+        self.discord_reconnects.fetch_add(1, Ordering::Relaxed);
+    }
+
+    #[cfg(feature = "discord")]
+    pub fn inc_rpc_update_failures(&self) {
+        self.rpc_update_failures.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn inc_log_lines_parsed(&self) {
+        self.log_lines_parsed.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn inc_log_lines_unmatched(&self) {
+        self.log_lines_unmatched.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn inc_transitions_rejected(&self) {
+        self.transitions_rejected.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn set_log_file_size(&self, bytes: u64) {
+        self.log_file_size.store(bytes, Ordering::Relaxed);
+    }
+
+    /// Set the game client resource gauges, or hide them with `None`
+    pub fn set_game_resources(&self, usage: Option<&ResourceUsage>) {
+        if let Some(usage) = usage {
+            self.game_cpu_percent_bits
+                .store((usage.cpu_percent as f64).to_bits(), Ordering::Relaxed);
+            self.game_memory_bytes.store(usage.memory_bytes, Ordering::Relaxed);
+            self.game_uptime_secs.store(usage.uptime_secs, Ordering::Relaxed);
+        }
+        self.game_resources.store(usage.is_some(), Ordering::Relaxed);
+    }
+
+    /// Record how long one iteration of the poll loop took (excluding the sleep)
+    pub fn observe_poll_loop(&self, elapsed: Duration) {
+        let micros = elapsed.as_micros() as u64;
+        self.poll_loop_micros.store(micros, Ordering::Relaxed);
+        self.poll_loop_micros_sum
+            .fetch_add(micros, Ordering::Relaxed);
+        self.poll_loop_count.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Render all metrics in the Prometheus text exposition format
+    pub fn render(&self) -> String {
+        let mut out = String::new();
+        let get = |value: &AtomicU64| value.load(Ordering::Relaxed);
+        let seconds = |micros: u64| micros as f64 / 1_000_000.0;
+
+        header(
+            &mut out,
+            "hytale_rpc_state_seconds_total",
+            "counter",
+            "Time spent in each game state",
+        );
+        let current = *self.current_state.lock().unwrap();
+        for (index, (kind, micros)) in STATE_KINDS.iter().zip(&self.state_micros).enumerate() {
+            // Include the time so far in the current state
+            let ongoing = current
+                .filter(|(current, _)| *current == index)
+                .map_or(0, |(_, since)| since.elapsed().as_micros() as u64);
+            let _ = writeln!(
+                out,
+                "hytale_rpc_state_seconds_total{{state=\"{}\"}} {}",
+                kind,
+                seconds(get(micros) + ongoing)
+            );
+        }
+
+        let simple = [
+            (
+                "hytale_rpc_discord_reconnects_total",
+                "counter",
+                "Reconnections to Discord after a lost connection",
+                get(&self.discord_reconnects) as f64,
+            ),
+            (
+                "hytale_rpc_rpc_update_failures_total",
+                "counter",
+                "Failed Discord presence updates",
+                get(&self.rpc_update_failures) as f64,
+            ),
+            (
+                "hytale_rpc_log_lines_parsed_total",
+                "counter",
+                "Log lines read from the game log",
+                get(&self.log_lines_parsed) as f64,
+            ),
+            (
+                "hytale_rpc_log_lines_unmatched_total",
+                "counter",
+                "Log lines that matched no known pattern",
+                get(&self.log_lines_unmatched) as f64,
+            ),
+            (
+                "hytale_rpc_transitions_rejected_total",
+                "counter",
+                "State changes from the log that the state machine does not allow",
+                get(&self.transitions_rejected) as f64,
+            ),
+            (
+                "hytale_rpc_log_file_size_bytes",
+                "gauge",
+                "Size of the log file being watched",
+                get(&self.log_file_size) as f64,
+            ),
+            (
+                "hytale_rpc_poll_loop_last_duration_seconds",
+                "gauge",
+                "Duration of the most recent poll loop iteration",
+                seconds(get(&self.poll_loop_micros)),
+            ),
+        ];
+        for (name, kind, help, value) in simple {
+            header(&mut out, name, kind, help);
+            let _ = writeln!(out, "{} {}", name, value);
+        }
+
+        if self.game_resources.load(Ordering::Relaxed) {
+            let game = [
+                (
+                    "hytale_rpc_game_cpu_percent",
+                    "CPU usage of the game client (100 is one core)",
+                    f64::from_bits(get(&self.game_cpu_percent_bits)),
+                ),
+                (
+                    "hytale_rpc_game_memory_bytes",
+                    "Resident memory of the game client",
+                    get(&self.game_memory_bytes) as f64,
+                ),
+                (
+                    "hytale_rpc_game_uptime_seconds",
+                    "Time since the game client started",
+                    get(&self.game_uptime_secs) as f64,
+                ),
+            ];
+            for (name, help, value) in game {
+                header(&mut out, name, "gauge", help);
+                let _ = writeln!(out, "{} {}", name, value);
+            }
+        }
+
+        header(
+            &mut out,
+            "hytale_rpc_poll_loop_duration_seconds",
+            "summary",
+            "Poll loop iteration duration",
+        );
+        let _ = writeln!(
+            out,
+            "hytale_rpc_poll_loop_duration_seconds_sum {}",
+            seconds(get(&self.poll_loop_micros_sum))
+        );
+        let _ = writeln!(
+            out,
+            "hytale_rpc_poll_loop_duration_seconds_count {}",
+            get(&self.poll_loop_count)
+        );
+
+        out
+    }
+}
+
+impl Default for Metrics {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+fn header(out: &mut String, name: &str, kind: &str, help: &str) {
+    let _ = writeln!(out, "# HELP {} {}", name, help);
+    let _ = writeln!(out, "# TYPE {} {}", name, kind);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_render() {
+        let metrics = Metrics::new();
+        let start = Instant::now() - Duration::from_secs(60);
+        metrics.enter_state("multiplayer", start);
+        metrics.enter_state("main_menu", start + Duration::from_millis(1500));
+        metrics.enter_state("multiplayer", start + Duration::from_millis(1500));
+        metrics.enter_state("loading", start + Duration::from_millis(2000));
+        metrics.enter_state("unknown", start + Duration::from_millis(2000));
+        metrics.inc_log_lines_parsed();
+        metrics.inc_log_lines_parsed();
+        metrics.inc_log_lines_unmatched();
+        metrics.inc_transitions_rejected();
+        metrics.set_log_file_size(4096);
+        metrics.observe_poll_loop(Duration::from_millis(3));
+
+        let text = metrics.render();
+        assert!(text.contains("hytale_rpc_state_seconds_total{state=\"multiplayer\"} 2\n"));
+        assert!(text.contains("hytale_rpc_state_seconds_total{state=\"loading\"} 0\n"));
+        assert!(text.contains("# TYPE hytale_rpc_log_lines_parsed_total counter\n"));
+        assert!(text.contains("hytale_rpc_log_lines_parsed_total 2\n"));
+        assert!(text.contains("hytale_rpc_log_lines_unmatched_total 1\n"));
+        assert!(text.contains("hytale_rpc_transitions_rejected_total 1\n"));
+        assert!(text.contains("hytale_rpc_log_file_size_bytes 4096\n"));
+        assert!(text.contains("hytale_rpc_poll_loop_duration_seconds_count 1\n"));
+        assert!(!text.contains("hytale_rpc_game_memory_bytes"));
+
+        metrics.set_game_resources(Some(&ResourceUsage {
+            cpu_percent: 12.5,
+            memory_bytes: 1024,
+            uptime_secs: 30,
+        }));
+        let text = metrics.render();
+        assert!(text.contains("hytale_rpc_game_cpu_percent 12.5\n"));
+        assert!(text.contains("hytale_rpc_game_memory_bytes 1024\n"));
+    }
+}
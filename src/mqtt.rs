@@ -0,0 +1,470 @@
+//! MQTT publisher for home-automation integration
+//!
+//! Publishes the current state as a retained message, session events as
+//! they happen, and optionally Home Assistant discovery configs. Availability
+//! turns "offline" when the app exits, or through the retained last will if
+//! it goes away without saying so; while the game is closed it stays
+//! "online" with an idle state.
+
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc;
+use std::sync::Arc;
+use std::thread;
+use std::time::Duration;
+
+use anyhow::{bail, Context, Result};
+use log::{debug, info, warn};
+use rumqttc::{Client, Event, LastWill, MqttOptions, Packet, QoS, Transport};
+use serde::Serialize;
+use serde_json::{json, Value};
+
+use crate::config::{AppConfig, GameState, MqttConfig};
+use crate::log_watcher::unix_now;
+use crate::sink::{PresenceSink, PresenceUpdate};
+use crate::state_machine::Transition;
+
+/// How long to wait for the broker to acknowledge the connection
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(10);
+
+const KEEP_ALIVE: Duration = Duration::from_secs(30);
+
+/// Broker address parsed from the configured URL
+#[derive(Debug, PartialEq, Eq)]
+struct BrokerAddress {
+    host: String,
+    port: u16,
+    tls: bool,
+}
+
+fn parse_broker_url(url: &str) -> Result<BrokerAddress> {
+    let (scheme, rest) = url.split_once("://").unwrap_or(("mqtt", url));
+    let tls = match scheme {
+        "mqtt" | "tcp" => false,
+        "mqtts" | "ssl" => true,
+        other => bail!("Unsupported MQTT URL scheme '{}'", other),
+    };
+
+    let rest = rest.trim_end_matches('/');
+    let (host, port) = match rest.rsplit_once(':') {
+        Some((host, port)) if !host.ends_with(':') => (
+            host,
+            port.parse()
+                .with_context(|| format!("Invalid MQTT port '{}'", port))?,
+        ),
+        _ => (rest, if tls { 8883 } else { 1883 }),
+    };
+    let host = host.trim_start_matches('[').trim_end_matches(']');
+    if host.is_empty() {
+        bail!("MQTT broker URL has no host");
+    }
+
+    Ok(BrokerAddress {
+        host: host.to_string(),
+        port,
+        tls,
+    })
+}
+
+/// Topics published under the configured prefix
+struct Topics {
+    availability: String,
+    state: String,
+    events: String,
+}
+
+impl Topics {
+    fn new(prefix: &str) -> Self {
+        let prefix = prefix.trim_end_matches('/');
+        Self {
+            availability: format!("{}/availability", prefix),
+            state: format!("{}/state", prefix),
+            events: format!("{}/events", prefix),
+        }
+    }
+}
+
+/// MQTT publisher
+pub struct MqttSink {
+    config: MqttConfig,
+    topics: Topics,
+    client: Option<Client>,
+    /// Cleared by the event loop thread when the connection drops
+    alive: Arc<AtomicBool>,
+    last_state: Option<String>,
+}
+
+impl MqttSink {
+    /// Create an MQTT publisher; the connection is made on `connect`
+    pub fn new(config: &MqttConfig) -> Self {
+        Self {
+            topics: Topics::new(&config.topic_prefix),
+            config: config.clone(),
+            client: None,
+            alive: Arc::new(AtomicBool::new(false)),
+            last_state: None,
+        }
+    }
+
+    fn publish(&self, topic: &str, retain: bool, payload: String) -> Result<()> {
+        let Some(ref client) = self.client else {
+            bail!("Not connected to MQTT broker");
+        };
+        if !self.alive.load(Ordering::SeqCst) {
+            bail!("MQTT connection lost");
+        }
+        client
+            .try_publish(topic, QoS::AtLeastOnce, retain, payload)
+            .context("Failed to publish MQTT message")
+    }
+
+    fn publish_discovery(&self) -> Result<()> {
+        for (topic, payload) in discovery_configs(&self.config, &self.topics) {
+            self.publish(&topic, true, payload.to_string())?;
+        }
+        Ok(())
+    }
+}
+
+impl PresenceSink for MqttSink {
+    fn name(&self) -> &'static str {
+        "MQTT"
+    }
+
+    fn connect(&mut self) -> Result<()> {
+        let broker = parse_broker_url(&self.config.broker_url)?;
+        info!("Connecting to MQTT broker {}:{}...", broker.host, broker.port);
+
+        let mut options = MqttOptions::new(&self.config.client_id, &broker.host, broker.port);
+        options.set_keep_alive(KEEP_ALIVE);
+        options.set_last_will(LastWill::new(
+            &self.topics.availability,
+            "offline",
+            QoS::AtLeastOnce,
+            true,
+        ));
+        if let Some(ref username) = self.config.username {
+            options.set_credentials(username, self.config.password.clone().unwrap_or_default());
+        }
+        if broker.tls || self.config.tls {
+            options.set_transport(Transport::tls_with_default_config());
+        }
+
+        let (client, mut connection) = Client::new(options, 32);
+        let (ready_tx, ready_rx) = mpsc::channel();
+        let alive = Arc::new(AtomicBool::new(false));
+        let thread_alive = alive.clone();
+
+        // Drive the connection; stop on the first error and let the sink set reconnect
+        thread::spawn(move || {
+            for event in connection.iter() {
+                match event {
+                    Ok(Event::Incoming(Packet::ConnAck(_))) => {
+                        thread_alive.store(true, Ordering::SeqCst);
+                        let _ = ready_tx.send(Ok(()));
+                    }
+                    Ok(_) => {}
+                    Err(e) => {
+                        if thread_alive.swap(false, Ordering::SeqCst) {
+                            warn!("MQTT connection lost: {}", e);
+                        } else {
+                            let _ = ready_tx.send(Err(e.to_string()));
+                        }
+                        break;
+                    }
+                }
+            }
+            debug!("MQTT event loop stopped");
+        });
+
+        match ready_rx.recv_timeout(CONNECT_TIMEOUT) {
+            Ok(Ok(())) => {}
+            Ok(Err(e)) => bail!("Failed to connect to MQTT broker: {}", e),
+            Err(_) => {
+                let _ = client.try_disconnect();
+                bail!("Timed out connecting to MQTT broker");
+            }
+        }
+
+        info!("Connected to MQTT broker");
+        self.client = Some(client);
+        self.alive = alive;
+        self.last_state = None;
+
+        self.publish(&self.topics.availability, true, "online".to_string())?;
+        if self.config.ha_discovery {
+            self.publish_discovery()?;
+        }
+        Ok(())
+    }
+
+    fn update(&mut self, update: &PresenceUpdate) -> Result<()> {
+        for transition in update.transitions {
+            let payload = transition_payload(transition, update.config).to_string();
+            self.publish(&self.topics.events, false, payload)?;
+        }
+
+        let payload = state_payload(update.state, update.session_start, update.config).to_string();
+        if self.last_state.as_ref() != Some(&payload) {
+            self.publish(&self.topics.state, true, payload.clone())?;
+            debug!("Published MQTT state: {}", payload);
+            self.last_state = Some(payload);
+        }
+        Ok(())
+    }
+
+    fn clear(&mut self, config: &AppConfig) -> Result<()> {
+        let payload = state_payload(&GameState::Unknown, None, config).to_string();
+        if self.last_state.as_ref() == Some(&payload) {
+            return Ok(());
+        }
+
+        let event = json!({ "event": "session_end", "at": unix_now() });
+        self.publish(&self.topics.events, false, event.to_string())?;
+        self.publish(&self.topics.state, true, payload.clone())?;
+        self.last_state = Some(payload);
+        Ok(())
+    }
+
+    /// Pausing only stops publishing: the game is still running, so the
+    /// retained state and availability stay as they are
+    fn pause(&mut self, _config: &AppConfig) -> Result<()> {
+        Ok(())
+    }
+
+    /// Leave the retained state idle; availability stays "online" because
+    /// the app is still running and will reconnect when the game starts
+    fn disconnect(&mut self) {
+        self.release(false);
+    }
+
+    fn shutdown(&mut self) {
+        self.release(true);
+    }
+}
+
+impl MqttSink {
+    /// Retained message to send before disconnecting: "offline" when the app
+    /// exits, otherwise the idle state unless it was already published
+    fn farewell(&self, shutdown: bool) -> Option<(&str, String)> {
+        if shutdown {
+            return Some((&self.topics.availability, "offline".to_string()));
+        }
+        let idle = state_payload(&GameState::Unknown, None, &AppConfig::default()).to_string();
+        (self.last_state.as_ref() != Some(&idle)).then_some((&self.topics.state, idle))
+    }
+
+    fn release(&mut self, shutdown: bool) {
+        if let Some(ref client) = self.client {
+            if self.alive.swap(false, Ordering::SeqCst) {
+                if let Some((topic, payload)) = self.farewell(shutdown) {
+                    let _ = client.try_publish(topic, QoS::AtLeastOnce, true, payload);
+                }
+            }
+            let _ = client.try_disconnect();
+            info!("Disconnected from MQTT broker");
+        }
+        self.client = None;
+        self.last_state = None;
+    }
+}
+
+impl Drop for MqttSink {
+    fn drop(&mut self) {
+        self.shutdown();
+    }
+}
+
+/// Retained state payload: the game state, redacted by the privacy toggles
+#[derive(Serialize)]
+struct StatePayload {
+    #[serde(flatten)]
+    state: GameState,
+    in_game: bool,
+    session_start: Option<i64>,
+}
+
+fn state_payload(state: &GameState, session_start: Option<i64>, config: &AppConfig) -> Value {
+    serde_json::to_value(StatePayload {
+        state: state.redacted(config),
+        in_game: state.is_in_game(),
+        session_start,
+    })
+    .unwrap_or_default()
+}
+
+/// Event payload for a transition, redacted by the privacy toggles
+fn transition_payload(transition: &Transition, config: &AppConfig) -> Value {
+    json!({
+        "event": "transition",
+        "from": transition.from.redacted(config),
+        "to": transition.to.redacted(config),
+        "cause": transition.cause,
+        "at": transition.at,
+    })
+}
+
+/// Home Assistant discovery topics and payloads for a sensor and a binary sensor
+fn discovery_configs(config: &MqttConfig, topics: &Topics) -> Vec<(String, Value)> {
+    let node_id: String = config
+        .client_id
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() { c } else { '_' })
+        .collect();
+    let prefix = config.discovery_prefix.trim_end_matches('/');
+    let device = json!({
+        "identifiers": [node_id],
+        "name": "Hytale RPC",
+        "manufacturer": "hytale-rpc",
+        "sw_version": env!("CARGO_PKG_VERSION"),
+    });
+
+    vec![
+        (
+            format!("{}/sensor/{}/state/config", prefix, node_id),
+            json!({
+                "name": "Hytale",
+                "unique_id": format!("{}_state", node_id),
+                "state_topic": topics.state,
+                "value_template": "{{ value_json.state }}",
+                "json_attributes_topic": topics.state,
+                "availability_topic": topics.availability,
+                "icon": "mdi:gamepad-variant",
+                "device": device,
+            }),
+        ),
+        (
+            format!("{}/binary_sensor/{}/in_game/config", prefix, node_id),
+            json!({
+                "name": "Hytale in game",
+                "unique_id": format!("{}_in_game", node_id),
+                "state_topic": topics.state,
+                "value_template": "{{ 'ON' if value_json.in_game else 'OFF' }}",
+                "availability_topic": topics.availability,
+                "device": device,
+            }),
+        ),
+    ]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::health::Health;
+
+    #[test]
+    fn test_parse_broker_url() {
+        assert_eq!(
+            parse_broker_url("mqtt://broker.local:1884").unwrap(),
+            BrokerAddress {
+                host: "broker.local".to_string(),
+                port: 1884,
+                tls: false,
+            }
+        );
+        assert_eq!(parse_broker_url("mqtts://broker.local").unwrap().port, 8883);
+        assert_eq!(parse_broker_url("[::1]:1883").unwrap().host, "::1");
+        assert!(parse_broker_url("http://broker.local").is_err());
+    }
+
+    #[test]
+    fn test_state_payload_and_discovery() {
+        let config = AppConfig {
+            show_server_ip: false,
+            ..Default::default()
+        };
+        let state = GameState::Multiplayer {
+            server_address: Some("smp.example.com:5520".to_string()),
+            server_name: None,
+        };
+
+        let payload = state_payload(&state, Some(10), &config);
+        assert_eq!(payload["state"], "multiplayer");
+        assert_eq!(payload["in_game"], true);
+        assert!(payload["server_address"].is_null());
+        assert_eq!(payload["session_start"], 10);
+
+        let topics = Topics::new("hytale-rpc/");
+        assert_eq!(topics.state, "hytale-rpc/state");
+        let discovery = discovery_configs(&config.mqtt, &topics);
+        assert_eq!(discovery[0].0, "homeassistant/sensor/hytale_rpc/state/config");
+        assert_eq!(discovery[1].1["state_topic"], "hytale-rpc/state");
+    }
+
+    #[test]
+    fn test_pause_publishes_nothing() {
+        let config = AppConfig::default();
+        let mut sink = MqttSink::new(&config.mqtt);
+
+        // Not connected, so anything published would fail
+        assert!(sink.pause(&config).is_ok());
+        assert!(sink.clear(&config).is_err());
+    }
+
+    #[test]
+    fn test_farewell() {
+        let config = AppConfig::default();
+        let mut sink = MqttSink::new(&config.mqtt);
+
+        let (topic, idle) = sink.farewell(false).unwrap();
+        assert_eq!(topic, "hytale-rpc/state");
+        let value: Value = serde_json::from_str(&idle).unwrap();
+        assert_eq!(value["state"], "unknown");
+        assert_eq!(value["in_game"], false);
+        let (topic, payload) = sink.farewell(true).unwrap();
+        assert_eq!((topic, payload.as_str()), ("hytale-rpc/availability", "offline"));
+
+        // Clearing already published the idle state
+        sink.last_state = Some(idle);
+        assert!(sink.farewell(false).is_none());
+        assert!(sink.farewell(true).is_some());
+    }
+
+    /// Needs a local broker, e.g. `mosquitto -p 1883`:
+    /// `HYTALE_RPC_TEST_MQTT_URL=mqtt://127.0.0.1:1883 cargo test -- --ignored`
+    #[test]
+    #[ignore]
+    fn test_publish_to_local_broker() {
+        let url = std::env::var("HYTALE_RPC_TEST_MQTT_URL")
+            .unwrap_or_else(|_| "mqtt://127.0.0.1:1883".to_string());
+        let mut config = AppConfig::default();
+        config.mqtt.broker_url = url.clone();
+        config.mqtt.client_id = "hytale-rpc-test".to_string();
+        config.mqtt.topic_prefix = "hytale-rpc-test".to_string();
+
+        let mut sink = MqttSink::new(&config.mqtt);
+        sink.connect().unwrap();
+        let state = GameState::Singleplayer {
+            world_name: "Orbis".to_string(),
+        };
+        sink.update(&PresenceUpdate {
+            state: &state,
+            session_start: Some(1),
+            game_running: true,
+            game_started_at: None,
+            transitions: &[],
+            resources: None,
+            health: &Health::new(),
+            config: &config,
+        })
+        .unwrap();
+
+        // A fresh subscriber must get the retained state
+        let broker = parse_broker_url(&url).unwrap();
+        let (client, mut connection) = Client::new(
+            MqttOptions::new("hytale-rpc-test-sub", broker.host, broker.port),
+            10,
+        );
+        client.subscribe("hytale-rpc-test/state", QoS::AtLeastOnce).unwrap();
+        let payload = connection
+            .iter()
+            .find_map(|event| match event.unwrap() {
+                Event::Incoming(Packet::Publish(p)) => Some(p.payload),
+                _ => None,
+            })
+            .unwrap();
+        let value: Value = serde_json::from_slice(&payload).unwrap();
+        assert_eq!(value["world_name"], "Orbis");
+
+        sink.disconnect();
+    }
+}
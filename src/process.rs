@@ -1,48 +1,263 @@
 //! Process detection module for Hytale
+//!
+//! The whole process table is only read while no game client is running.
+//! Once one is found, the tracked processes are refreshed by PID and, on
+//! Linux, their exit is noticed through a pidfd. Process starts have no
+//! event an unprivileged app can listen to (the netlink proc connector needs
+//! `CAP_NET_ADMIN`), so launchers and further clients started meanwhile are
+//! found by a lookup that only reads process names, every few refreshes.
 
-use sysinfo::System;
+use std::fmt;
+use std::path::PathBuf;
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::time::Duration;
+
+use log::debug;
+use sysinfo::{
+    Pid, Process, ProcessRefreshKind, ProcessStatus, ProcessesToUpdate, System, UpdateKind,
+};
+
+use crate::config::{HYTALE_GAME_PROCESSES, HYTALE_LAUNCHER_PROCESSES};
+use crate::resources::ResourceUsage;
+
+/// How many parent processes are checked when detecting the runtime
+const MAX_ANCESTORS: usize = 8;
+
+/// While a game client is tracked, process names are looked up every this
+/// many refreshes, to notice launchers and further clients
+const LOOKUP_EVERY: u32 = 5;
+
+/// How the game client is being run
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Runtime {
+    Native,
+    Wine,
+    Proton,
+    Flatpak,
+}
+
+impl fmt::Display for Runtime {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            Runtime::Native => "native",
+            Runtime::Wine => "Wine",
+            Runtime::Proton => "Proton",
+            Runtime::Flatpak => "Flatpak",
+        };
+        f.write_str(name)
+    }
+}
+
+/// A running game process
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize)]
+pub struct DetectedProcess {
+    pub pid: u32,
+    pub name: String,
+    pub exe: Option<PathBuf>,
+    pub cmdline: Vec<String>,
+    /// Unix timestamp (seconds) at which the process started
+    pub start_time: u64,
+    pub parent: Option<u32>,
+    pub runtime: Runtime,
+}
+
+impl DetectedProcess {
+    /// Server the game was launched into with `--server`, if any
+    pub fn server_arg(&self) -> Option<&str> {
+        arg_value(&self.cmdline, "--server")
+    }
+
+    /// World the game was launched into with `--world`, if any
+    pub fn world_arg(&self) -> Option<&str> {
+        arg_value(&self.cmdline, "--world")
+    }
+}
 
 /// Process detector for monitoring Hytale
 pub struct ProcessDetector {
     system: System,
+    /// Game and launcher processes found by the last full scan, followed
+    /// until they exit
+    tracked: Vec<Tracked>,
+    exit_tx: Sender<u32>,
+    exit_rx: Receiver<u32>,
+    /// Exits received while waiting, handled on the next refresh
+    exited: Vec<u32>,
+    /// Refreshes since the whole process table was last read
+    since_lookup: u32,
+}
+
+/// A process being followed by the detector
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Tracked {
+    pid: Pid,
+    /// Guards against the PID being reused by another process
+    start_time: u64,
+    is_game: bool,
 }
 
 impl ProcessDetector {
     /// Create a new process detector
     pub fn new() -> Self {
-        Self {
+        let (exit_tx, exit_rx) = mpsc::channel();
+        let mut detector = Self {
             system: System::new_all(),
-        }
+            tracked: Vec::new(),
+            exit_tx,
+            exit_rx,
+            exited: Vec::new(),
+            since_lookup: 0,
+        };
+        detector.track_matching(&[]);
+        detector
     }
 
     /// Refresh process list
+    ///
+    /// While no game client is running, the whole process table is scanned.
+    /// Once one is found, only the tracked processes are refreshed, and the
+    /// other processes are only checked by name every few refreshes.
     pub fn refresh(&mut self) {
-        self.system.refresh_processes(sysinfo::ProcessesToUpdate::All);
+        self.exited.extend(self.exit_rx.try_iter());
+        let exited = std::mem::take(&mut self.exited);
+        self.tracked.retain(|t| !exited.contains(&t.pid.as_u32()));
+
+        self.since_lookup += 1;
+        if self.since_lookup >= LOOKUP_EVERY && self.tracked.iter().any(|t| t.is_game) {
+            self.since_lookup = 0;
+            self.system
+                .refresh_processes_specifics(ProcessesToUpdate::All, ProcessRefreshKind::new());
+            self.track_matching(&exited);
+        }
+
+        if self.tracked.iter().any(|t| t.is_game) {
+            let system = &mut self.system;
+            self.tracked.retain(|t| {
+                let updated = system.refresh_processes_specifics(
+                    ProcessesToUpdate::Some(&[t.pid]),
+                    refresh_kind(),
+                );
+                updated > 0 && system.process(t.pid).is_some_and(|p| p.start_time() == t.start_time)
+            });
+        }
+
+        if !self.tracked.iter().any(|t| t.is_game) {
+            self.since_lookup = 0;
+            self.system
+                .refresh_processes_specifics(ProcessesToUpdate::All, refresh_kind());
+            self.track_matching(&exited);
+        }
+    }
+
+    /// Sleep for up to `timeout`, returning early when a tracked process exits
+    pub fn wait(&mut self, timeout: Duration) {
+        if let Ok(pid) = self.exit_rx.recv_timeout(timeout) {
+            debug!("Tracked process {} exited", pid);
+            self.exited.push(pid);
+        }
+    }
+
+    /// Follow every running game and launcher process, except the ones
+    /// that are known to have exited but may still be listed
+    fn track_matching(&mut self, exited: &[u32]) {
+        let mut tracked = Vec::new();
+        for (pid, process) in self.system.processes() {
+            if exited.contains(&pid.as_u32()) || matches!(process.status(), ProcessStatus::Zombie | ProcessStatus::Dead) {
+                continue;
+            }
+            let name = process.name().to_string_lossy();
+            let is_game = matches_name(&name, HYTALE_GAME_PROCESSES);
+            if !is_game && !matches_name(&name, HYTALE_LAUNCHER_PROCESSES) {
+                continue;
+            }
+
+            let entry = Tracked {
+                pid: *pid,
+                start_time: process.start_time(),
+                is_game,
+            };
+            if !self.tracked.contains(&entry) {
+                watch_exit(pid.as_u32(), self.exit_tx.clone());
+            }
+            tracked.push(entry);
+        }
+        self.tracked = tracked;
+    }
+
+    /// Tracked processes that are still in the process table
+    fn tracked_processes(&self, game: bool) -> impl Iterator<Item = (Pid, &Process)> {
+        self.tracked
+            .iter()
+            .filter(move |t| t.is_game == game)
+            .filter_map(|t| Some((t.pid, self.system.process(t.pid)?)))
     }
 
     /// Check if Hytale Game Client is running
     pub fn is_game_running(&self) -> bool {
-        self.is_process_running(crate::config::HYTALE_GAME_PROCESSES)
+        self.tracked_processes(true).next().is_some()
     }
 
     /// Check if Hytale Launcher is running
     pub fn is_launcher_running(&self) -> bool {
-        self.is_process_running(crate::config::HYTALE_LAUNCHER_PROCESSES)
-    }
-
-    /// Check if any of the given process names are running
-    fn is_process_running(&self, names: &[&str]) -> bool {
-        for process in self.system.processes().values() {
-            let process_name = process.name().to_string_lossy().to_lowercase();
-            for name in names {
-                if process_name == name.to_lowercase()
-                    || process_name.starts_with(&format!("{}.", name.to_lowercase()))
-                {
-                    return true;
-                }
+        self.tracked_processes(false).next().is_some()
+    }
+
+    /// Running game client processes, most recently started first
+    pub fn game_processes(&self) -> Vec<DetectedProcess> {
+        let mut processes: Vec<DetectedProcess> = self
+            .tracked_processes(true)
+            .map(|(pid, process)| self.describe(pid, process))
+            .collect();
+        processes.sort_by_key(|p| std::cmp::Reverse(p.start_time));
+        processes
+    }
+
+    /// CPU and memory use of the most recently started game client, from
+    /// the data read by the last refresh
+    pub fn game_usage(&self) -> Option<ResourceUsage> {
+        let (_, process) = self
+            .tracked_processes(true)
+            .max_by_key(|(_, process)| process.start_time())?;
+        Some(ResourceUsage {
+            cpu_percent: process.cpu_usage(),
+            memory_bytes: process.memory(),
+            uptime_secs: process.run_time(),
+        })
+    }
+
+    /// The most recently started game client process
+    pub fn game_process(&self) -> Option<DetectedProcess> {
+        self.game_processes().into_iter().next()
+    }
+
+    fn describe(&self, pid: Pid, process: &Process) -> DetectedProcess {
+        let exe = process.exe().map(|exe| exe.to_path_buf());
+        let cmdline: Vec<String> = process
+            .cmd()
+            .iter()
+            .map(|arg| arg.to_string_lossy().into_owned())
+            .collect();
+
+        let mut ancestors = Vec::new();
+        let mut parent = process.parent();
+        while let Some(process) = parent.and_then(|pid| self.system.process(pid)) {
+            if ancestors.len() >= MAX_ANCESTORS {
+                break;
             }
+            ancestors.push(process.name().to_string_lossy().into_owned());
+            parent = process.parent();
+        }
+
+        DetectedProcess {
+            pid: pid.as_u32(),
+            name: process.name().to_string_lossy().into_owned(),
+            runtime: detect_runtime(exe.as_deref(), &cmdline, &ancestors),
+            exe,
+            cmdline,
+            start_time: process.start_time(),
+            parent: process.parent().map(|pid| pid.as_u32()),
         }
-        false
     }
 
     /// Get all running process names (for debugging)
@@ -56,6 +271,101 @@ impl ProcessDetector {
     }
 }
 
+/// What to read for each refreshed process
+fn refresh_kind() -> ProcessRefreshKind {
+    ProcessRefreshKind::new()
+        .with_memory()
+        .with_cpu()
+        .with_exe(UpdateKind::OnlyIfNotSet)
+        .with_cmd(UpdateKind::OnlyIfNotSet)
+}
+
+/// Send `pid` on `exits` as soon as the process exits, using a pidfd.
+/// Returns false if that isn't supported, in which case exits are only
+/// noticed on the next refresh.
+#[cfg(target_os = "linux")]
+fn watch_exit(pid: u32, exits: Sender<u32>) -> bool {
+    use std::os::fd::{AsRawFd, FromRawFd, OwnedFd};
+    use std::thread;
+
+    // SAFETY: pidfd_open takes a PID and flags and returns a new fd or -1
+    let fd = unsafe { libc::syscall(libc::SYS_pidfd_open, pid as libc::pid_t, 0) };
+    if fd < 0 {
+        debug!("pidfd_open({}) failed: {}", pid, std::io::Error::last_os_error());
+        return false;
+    }
+    // SAFETY: the fd was just created and nothing else owns it
+    let fd = unsafe { OwnedFd::from_raw_fd(fd as i32) };
+
+    thread::spawn(move || {
+        let mut pollfd = libc::pollfd {
+            fd: fd.as_raw_fd(),
+            events: libc::POLLIN,
+            revents: 0,
+        };
+        loop {
+            // SAFETY: pollfd points to one valid pollfd for the whole call
+            let result = unsafe { libc::poll(&mut pollfd, 1, -1) };
+            if result >= 0 || std::io::Error::last_os_error().kind() != std::io::ErrorKind::Interrupted {
+                break;
+            }
+        }
+        let _ = exits.send(pid);
+    });
+    true
+}
+
+#[cfg(not(target_os = "linux"))]
+fn watch_exit(_pid: u32, _exits: Sender<u32>) -> bool {
+    false
+}
+
+/// Check if a process name is one of `names`, ignoring case and extensions
+fn matches_name(process_name: &str, names: &[&str]) -> bool {
+    let process_name = process_name.to_lowercase();
+    names.iter().any(|name| {
+        let name = name.to_lowercase();
+        process_name == name || process_name.starts_with(&format!("{}.", name))
+    })
+}
+
+/// Work out how the game is run from its executable, command line and the
+/// names of its parent processes
+fn detect_runtime(exe: Option<&std::path::Path>, cmdline: &[String], ancestors: &[String]) -> Runtime {
+    if cfg!(windows) {
+        return Runtime::Native;
+    }
+
+    let exe = exe.map(|exe| exe.to_string_lossy().to_lowercase()).unwrap_or_default();
+    let args = cmdline.join(" ").to_lowercase();
+    let ancestors: Vec<String> = ancestors.iter().map(|name| name.to_lowercase()).collect();
+    let any_ancestor = |f: &dyn Fn(&str) -> bool| ancestors.iter().any(|name| f(name));
+
+    if exe.starts_with("/app/") || any_ancestor(&|name| name == "bwrap" || name == "flatpak") {
+        Runtime::Flatpak
+    } else if args.contains("proton") || args.contains("compatdata") || any_ancestor(&|name| name.contains("proton")) {
+        Runtime::Proton
+    } else if exe.contains("wine") || exe.ends_with(".exe") || args.contains(".exe") || any_ancestor(&|name| name.contains("wine")) {
+        Runtime::Wine
+    } else {
+        Runtime::Native
+    }
+}
+
+/// Value of `--name value` or `--name=value` in a command line
+fn arg_value<'a>(cmdline: &'a [String], name: &str) -> Option<&'a str> {
+    let mut args = cmdline.iter();
+    while let Some(arg) = args.next() {
+        if arg == name {
+            return args.next().map(|value| value.as_str());
+        }
+        if let Some(value) = arg.strip_prefix(name).and_then(|rest| rest.strip_prefix('=')) {
+            return Some(value);
+        }
+    }
+    None
+}
+
 impl Default for ProcessDetector {
     fn default() -> Self {
         Self::new()
@@ -73,4 +383,62 @@ mod tests {
         let _ = detector.is_game_running();
         let _ = detector.is_launcher_running();
     }
+
+    #[test]
+    #[cfg(target_os = "linux")]
+    fn test_watch_exit() {
+        let mut child = std::process::Command::new("sleep").arg("30").spawn().unwrap();
+        let (tx, rx) = mpsc::channel();
+        assert!(watch_exit(child.id(), tx));
+        assert!(rx.recv_timeout(Duration::from_millis(100)).is_err());
+
+        child.kill().unwrap();
+        child.wait().unwrap();
+        assert_eq!(rx.recv_timeout(Duration::from_secs(5)), Ok(child.id()));
+    }
+
+    #[test]
+    fn test_arg_value() {
+        let cmdline: Vec<String> = ["HytaleClient", "--server", "play.example.com:5520", "--world=My World"]
+            .iter()
+            .map(|arg| arg.to_string())
+            .collect();
+        assert_eq!(arg_value(&cmdline, "--server"), Some("play.example.com:5520"));
+        assert_eq!(arg_value(&cmdline, "--world"), Some("My World"));
+        assert_eq!(arg_value(&cmdline, "--missing"), None);
+        assert_eq!(arg_value(&cmdline[..2], "--server"), None);
+    }
+
+    #[test]
+    #[cfg(not(windows))]
+    fn test_detect_runtime() {
+        let path = |p: &str| Some(std::path::PathBuf::from(p));
+        let args = |a: &[&str]| a.iter().map(|s| s.to_string()).collect::<Vec<_>>();
+        let names = |a: &[&str]| a.iter().map(|s| s.to_string()).collect::<Vec<_>>();
+
+        assert_eq!(
+            detect_runtime(path("/opt/hytale/HytaleClient").as_deref(), &[], &names(&["bash", "systemd"])),
+            Runtime::Native
+        );
+        assert_eq!(
+            detect_runtime(path("/app/bin/HytaleClient").as_deref(), &[], &[]),
+            Runtime::Flatpak
+        );
+        assert_eq!(
+            detect_runtime(
+                path("/usr/bin/wine64-preloader").as_deref(),
+                &args(&["Z:\\hytale\\HytaleClient.exe"]),
+                &[]
+            ),
+            Runtime::Wine
+        );
+        assert_eq!(
+            detect_runtime(
+                path("/usr/bin/wine64-preloader").as_deref(),
+                &args(&["HytaleClient.exe"]),
+                &names(&["proton", "steam"])
+            ),
+            Runtime::Proton
+        );
+    }
 }
@@ -0,0 +1,154 @@
+//! CPU and memory usage of the game client
+//!
+//! Usage is read from the process data [`ProcessDetector`] already refreshes
+//! on every poll, so sampling needs no extra system calls.
+//!
+//! [`ProcessDetector`]: crate::process::ProcessDetector
+
+use serde::{Deserialize, Serialize};
+
+/// One sample of the game process's resource use
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+pub struct ResourceUsage {
+    /// CPU usage since the previous refresh; 100% is one full core
+    pub cpu_percent: f32,
+    /// Resident memory
+    pub memory_bytes: u64,
+    /// Seconds since the process started
+    pub uptime_secs: u64,
+}
+
+impl ResourceUsage {
+    /// Short human-readable form, e.g. `CPU 42%, RAM 1.5 GiB`
+    pub fn summary(&self) -> String {
+        format!(
+            "CPU {:.0}%, RAM {:.1} GiB",
+            self.cpu_percent,
+            self.memory_bytes as f64 / (1024.0 * 1024.0 * 1024.0)
+        )
+    }
+}
+
+/// Minimum, average and maximum of a value over a session
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct Range {
+    pub min: f64,
+    pub avg: f64,
+    pub max: f64,
+}
+
+/// Resource use over a whole session
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ResourceSummary {
+    pub samples: u64,
+    pub cpu_percent: Range,
+    pub memory_bytes: Range,
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Accumulator {
+    min: f64,
+    max: f64,
+    sum: f64,
+}
+
+impl Accumulator {
+    fn new(value: f64) -> Self {
+        Self {
+            min: value,
+            max: value,
+            sum: value,
+        }
+    }
+
+    fn add(&mut self, value: f64) {
+        self.min = self.min.min(value);
+        self.max = self.max.max(value);
+        self.sum += value;
+    }
+
+    fn range(&self, samples: u64) -> Range {
+        Range {
+            min: self.min,
+            avg: self.sum / samples as f64,
+            max: self.max,
+        }
+    }
+}
+
+/// Collects samples into min/avg/max per session
+#[derive(Debug, Default)]
+pub struct ResourceStats {
+    samples: u64,
+    cpu: Option<Accumulator>,
+    memory: Option<Accumulator>,
+}
+
+impl ResourceStats {
+    /// Create empty statistics
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add a sample
+    pub fn record(&mut self, usage: &ResourceUsage) {
+        self.samples += 1;
+        let add = |acc: &mut Option<Accumulator>, value: f64| match acc {
+            Some(acc) => acc.add(value),
+            None => *acc = Some(Accumulator::new(value)),
+        };
+        add(&mut self.cpu, usage.cpu_percent as f64);
+        add(&mut self.memory, usage.memory_bytes as f64);
+    }
+
+    /// Min/avg/max of the samples so far, if there are any
+    pub fn summary(&self) -> Option<ResourceSummary> {
+        Some(ResourceSummary {
+            samples: self.samples,
+            cpu_percent: self.cpu?.range(self.samples),
+            memory_bytes: self.memory?.range(self.samples),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_resource_stats() {
+        let mut stats = ResourceStats::new();
+        assert!(stats.summary().is_none());
+
+        for (cpu, memory) in [(10.0, 1000), (30.0, 3000), (20.0, 2000)] {
+            stats.record(&ResourceUsage {
+                cpu_percent: cpu,
+                memory_bytes: memory,
+                uptime_secs: 0,
+            });
+        }
+
+        let summary = stats.summary().unwrap();
+        assert_eq!(summary.samples, 3);
+        assert_eq!(
+            summary.cpu_percent,
+            Range {
+                min: 10.0,
+                avg: 20.0,
+                max: 30.0
+            }
+        );
+        assert_eq!(summary.memory_bytes.max, 3000.0);
+        assert_eq!(summary.memory_bytes.avg, 2000.0);
+    }
+
+    #[test]
+    fn test_usage_summary() {
+        let usage = ResourceUsage {
+            cpu_percent: 42.4,
+            memory_bytes: 3 * 1024 * 1024 * 1024 / 2,
+            uptime_secs: 0,
+        };
+        assert_eq!(usage.summary(), "CPU 42%, RAM 1.5 GiB");
+    }
+}
@@ -1,20 +1,90 @@
 //! Discord Rich Presence module
+//!
+//! Discord rate-limits activity updates, so changes are paced: the first
+//! change after a quiet period is sent right away, later ones are held back
+//! and only the newest is sent once the limits allow (on a later poll).
+//! Clearing the presence counts against the same limits.
 
-use std::time::{SystemTime, UNIX_EPOCH};
+use std::collections::VecDeque;
+use std::time::{Duration, Instant};
 
 use anyhow::Result;
 use discord_rich_presence::{activity, DiscordIpc, DiscordIpcClient};
 use log::{debug, error, info, warn};
 
 use crate::config::{AppConfig, GameState, CLIENT_ID, LARGE_IMAGE, LARGE_TEXT};
+use crate::events::{self, Event};
+use crate::metrics;
+use crate::sink::{PresenceSink, PresenceUpdate};
+
+/// Most activity updates Discord accepts per [`RATE_LIMIT_WINDOW`]
+const RATE_LIMIT_UPDATES: usize = 5;
+
+/// Window over which Discord counts activity updates; a clear counts too
+const RATE_LIMIT_WINDOW: Duration = Duration::from_secs(20);
+
+/// Least time between two activity updates
+const MIN_UPDATE_INTERVAL: Duration = Duration::from_secs(2);
+
+/// Decides when the next activity update may be sent
+#[derive(Debug, Default)]
+struct Pacer {
+    /// When the updates within the rate limit window were sent, oldest first
+    sent: VecDeque<Instant>,
+    /// Kind of the state on display and since when
+    shown: Option<(&'static str, Instant)>,
+}
+
+impl Pacer {
+    /// Earliest time an update to a state of `kind` may be sent, given
+    /// how long the state on display must stay
+    fn ready_at(&mut self, now: Instant, kind: &str, min_display: Duration) -> Instant {
+        while self
+            .sent
+            .front()
+            .is_some_and(|sent| now.duration_since(*sent) >= RATE_LIMIT_WINDOW)
+        {
+            self.sent.pop_front();
+        }
+
+        let mut ready = now;
+        if let Some(last) = self.sent.back() {
+            ready = ready.max(*last + MIN_UPDATE_INTERVAL);
+        }
+        if self.sent.len() >= RATE_LIMIT_UPDATES {
+            ready = ready.max(self.sent[self.sent.len() - RATE_LIMIT_UPDATES] + RATE_LIMIT_WINDOW);
+        }
+        if let Some((shown, since)) = self.shown {
+            if shown != kind {
+                ready = ready.max(since + min_display);
+            }
+        }
+        ready
+    }
+
+    /// Remember an update showing a state of `kind` (`None` when cleared)
+    fn record(&mut self, now: Instant, kind: Option<&'static str>) {
+        self.sent.push_back(now);
+        self.shown = match (kind, self.shown) {
+            (Some(kind), Some((shown, since))) if shown == kind => Some((shown, since)),
+            (Some(kind), _) => Some((kind, now)),
+            (None, _) => None,
+        };
+    }
+}
 
 /// Discord RPC manager
 pub struct DiscordRpc {
     client: Option<DiscordIpcClient>,
     connected: bool,
-    start_timestamp: Option<i64>,
+    /// Set when an update failed, so the next connect counts as a reconnect
+    connection_lost: bool,
     last_state: Option<GameState>,
+    last_session_start: Option<i64>,
     last_config_signature: Option<(bool, bool)>, // Track config changes to force update
+    pacer: Pacer,
+    /// Set when a clear was held back by the rate limit
+    clear_pending: bool,
 }
 
 impl DiscordRpc {
@@ -23,19 +93,23 @@ impl DiscordRpc {
         Self {
             client: None,
             connected: false,
-            start_timestamp: None,
+            connection_lost: false,
             last_state: None,
+            last_session_start: None,
             last_config_signature: None,
+            pacer: Pacer::default(),
+            clear_pending: false,
         }
     }
+}
 
-    /// Check if connected to Discord
-    pub fn is_connected(&self) -> bool {
-        self.connected
+impl PresenceSink for DiscordRpc {
+    fn name(&self) -> &'static str {
+        "Discord"
     }
 
     /// Connect to Discord RPC
-    pub fn connect(&mut self) -> Result<()> {
+    fn connect(&mut self) -> Result<()> {
         if self.connected {
             return Ok(());
         }
@@ -47,8 +121,13 @@ impl DiscordRpc {
         match client.connect() {
             Ok(_) => {
                 info!("Connected to Discord RPC");
+                if self.connection_lost {
+                    metrics::global().inc_discord_reconnects();
+                    self.connection_lost = false;
+                }
                 self.client = Some(client);
                 self.connected = true;
+                events::emit(Event::DiscordConnected);
                 Ok(())
             }
             Err(e) => {
@@ -59,36 +138,87 @@ impl DiscordRpc {
     }
 
     /// Disconnect from Discord RPC
-    pub fn disconnect(&mut self) {
+    fn disconnect(&mut self) {
         if let Some(ref mut client) = self.client {
             if let Err(e) = client.close() {
                 error!("Error closing Discord RPC: {}", e);
             }
+            events::emit(Event::DiscordDisconnected { error: None });
         }
         self.client = None;
         self.connected = false;
-        self.start_timestamp = None;
         self.last_state = None;
+        self.pacer.shown = None;
+        self.clear_pending = false;
         info!("Disconnected from Discord RPC");
     }
 
-    /// Clear the Discord presence
-    pub fn clear(&mut self) -> Result<()> {
-        if let Some(ref mut client) = self.client {
-            client.clear_activity()
-                .map_err(|e| anyhow::anyhow!("Failed to clear activity: {}", e))?;
-            self.last_state = None;
-            debug!("Cleared Discord presence");
+    /// Clear the Discord presence, or hold the clear back until the rate
+    /// limit allows it (sent by `flush` unless an update replaces it)
+    fn clear(&mut self, _config: &AppConfig) -> Result<()> {
+        let Some(ref mut client) = self.client else {
+            return Ok(());
+        };
+        self.last_state = None;
+
+        let now = Instant::now();
+        let ready_at = self.pacer.ready_at(now, GameState::Unknown.kind(), Duration::ZERO);
+        if ready_at > now {
+            debug!(
+                "Holding back Discord clear for {:.1}s",
+                (ready_at - now).as_secs_f32()
+            );
+            self.clear_pending = true;
+            return Ok(());
+        }
+
+        client.clear_activity()
+            .map_err(|e| anyhow::anyhow!("Failed to clear activity: {}", e))?;
+        self.pacer.record(now, None);
+        self.clear_pending = false;
+        debug!("Cleared Discord presence");
+        Ok(())
+    }
+
+    fn flush(&mut self, config: &AppConfig) -> Result<()> {
+        if self.clear_pending {
+            self.clear(config)?;
         }
         Ok(())
     }
 
     /// Update Discord presence with the current game state
-    pub fn update(&mut self, state: &GameState, config: &AppConfig) -> Result<()> {
+    fn update(&mut self, update: &PresenceUpdate) -> Result<()> {
+        let state = update.state;
+        let config = update.config;
         let config_signature = (config.show_world_name, config.show_server_ip);
         
-        // Skip update if state and config haven't changed
-        if self.last_state.as_ref() == Some(state) && self.last_config_signature == Some(config_signature) {
+        // Skip update if state and config haven't changed; this also drops
+        // a held-back update that the state has since returned from
+        if self.last_state.as_ref() == Some(state)
+            && self.last_session_start == update.session_start
+            && self.last_config_signature == Some(config_signature)
+        {
+            return Ok(());
+        }
+
+        // Hold the update back until the rate limit and the minimum display
+        // time allow it; the newest state is sent on a later poll
+        let now = Instant::now();
+        let min_display = Duration::from_secs(
+            self.last_state
+                .as_ref()
+                .and_then(|shown| config.discord.min_display_secs.get(shown.kind()))
+                .copied()
+                .unwrap_or(0),
+        );
+        let ready_at = self.pacer.ready_at(now, state.kind(), min_display);
+        if ready_at > now {
+            debug!(
+                "Holding back Discord update to {} for {:.1}s",
+                state.kind(),
+                (ready_at - now).as_secs_f32()
+            );
             return Ok(());
         }
 
@@ -97,20 +227,8 @@ impl DiscordRpc {
             None => return Err(anyhow::anyhow!("Not connected to Discord")),
         };
 
-        // Set start timestamp when entering game
-        if state.is_in_game() && !self.last_state.as_ref().map(|s| s.is_in_game()).unwrap_or(false) {
-            self.start_timestamp = Some(
-                SystemTime::now()
-                    .duration_since(UNIX_EPOCH)
-                    .unwrap()
-                    .as_secs() as i64,
-            );
-        }
-
-        // Clear timestamp when leaving game
-        if !state.is_in_game() {
-            self.start_timestamp = None;
-        }
+        // Elapsed time is only shown while in-game
+        let start_timestamp = update.session_start.filter(|_| state.is_in_game());
 
         let details = state.details();
         let state_str = state.state(config);
@@ -132,21 +250,29 @@ impl DiscordRpc {
             )]);
 
         // Add timestamp if in-game
-        if let Some(timestamp) = self.start_timestamp {
+        if let Some(timestamp) = start_timestamp {
             activity_builder = activity_builder.timestamps(activity::Timestamps::new().start(timestamp));
         }
 
         match client.set_activity(activity_builder) {
             Ok(_) => {
+                self.pacer.record(now, Some(state.kind()));
+                self.clear_pending = false;
                 self.last_state = Some(state.clone());
+                self.last_session_start = update.session_start;
                 self.last_config_signature = Some(config_signature);
                 debug!("Discord presence updated successfully");
                 Ok(())
             }
             Err(e) => {
                 error!("Failed to update Discord presence: {}", e);
+                metrics::global().inc_rpc_update_failures();
                 // Try to reconnect on error
                 self.connected = false;
+                self.connection_lost = true;
+                events::emit(Event::DiscordDisconnected {
+                    error: Some(e.to_string()),
+                });
                 self.client = None;
                 Err(anyhow::anyhow!("Failed to update presence: {}", e))
             }
@@ -165,3 +291,54 @@ impl Drop for DiscordRpc {
         self.disconnect();
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rate_limit() {
+        let start = Instant::now();
+        let mut pacer = Pacer::default();
+
+        // Leading edge: the first update goes out at once
+        assert_eq!(pacer.ready_at(start, "loading", Duration::ZERO), start);
+        pacer.record(start, Some("loading"));
+
+        // Updates right after it wait for the interval
+        let soon = start + Duration::from_millis(500);
+        assert_eq!(pacer.ready_at(soon, "loading", Duration::ZERO), start + MIN_UPDATE_INTERVAL);
+
+        // A burst uses up the window
+        let mut now = start;
+        for _ in 1..RATE_LIMIT_UPDATES {
+            now += MIN_UPDATE_INTERVAL;
+            assert_eq!(pacer.ready_at(now, "loading", Duration::ZERO), now);
+            pacer.record(now, Some("loading"));
+        }
+        now += MIN_UPDATE_INTERVAL;
+        assert_eq!(pacer.ready_at(now, "loading", Duration::ZERO), start + RATE_LIMIT_WINDOW);
+
+        // Once the oldest update leaves the window there is room again
+        let later = start + RATE_LIMIT_WINDOW;
+        assert_eq!(pacer.ready_at(later, "loading", Duration::ZERO), later);
+    }
+
+    #[test]
+    fn test_min_display() {
+        let start = Instant::now();
+        let min_display = Duration::from_secs(10);
+        let mut pacer = Pacer::default();
+        pacer.record(start, Some("main_menu"));
+
+        let now = start + Duration::from_secs(3);
+        assert_eq!(pacer.ready_at(now, "loading", min_display), start + min_display);
+        // Updates within the same kind of state don't have to wait for it
+        assert_eq!(pacer.ready_at(now, "main_menu", min_display), now);
+
+        // Clearing starts over
+        pacer.record(now, None);
+        let later = now + MIN_UPDATE_INTERVAL;
+        assert_eq!(pacer.ready_at(later, "loading", min_display), later);
+    }
+}
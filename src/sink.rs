@@ -0,0 +1,416 @@
+//! Presence sinks: outputs that receive the current game state
+//!
+//! Every output (Discord, HTTP, files, webhooks, ...) implements
+//! [`PresenceSink`]. The [`SinkSet`] drives them from the main loop and keeps
+//! connection and retry state per sink, so one failing output can't block
+//! the others. Transitions a sink misses while it isn't connected are kept
+//! and replayed once it is, up to [`MAX_PENDING_TRANSITIONS`].
+
+use std::time::{Duration, Instant};
+
+use anyhow::Result;
+use log::{info, warn};
+
+use crate::config::{AppConfig, GameState, POLL_INTERVAL_MS};
+use crate::health::Health;
+use crate::resources::ResourceUsage;
+use crate::state_machine::Transition;
+
+/// Longest delay between reconnect attempts of a failing sink
+const MAX_RETRY_DELAY: Duration = Duration::from_secs(60);
+
+/// Most transitions kept for a sink that isn't connected
+pub const MAX_PENDING_TRANSITIONS: usize = 100;
+
+/// Everything a sink gets on each update
+pub struct PresenceUpdate<'a> {
+    /// Current state (`Launcher` when only the launcher is running)
+    pub state: &'a GameState,
+    /// Unix timestamp (seconds) at which the current in-game session started
+    pub session_start: Option<i64>,
+    /// Whether the game client itself is running
+    pub game_running: bool,
+    /// Unix timestamp (seconds) at which the game process started, while it runs
+    pub game_started_at: Option<i64>,
+    /// Transitions observed by the log watcher since the previous update,
+    /// or since the last one this sink received
+    pub transitions: &'a [Transition],
+    /// CPU and memory use of the game client, when it is running
+    pub resources: Option<ResourceUsage>,
+    /// Warnings and errors the game logged this session
+    pub health: &'a Health,
+    pub config: &'a AppConfig,
+}
+
+/// An output for the current presence
+pub trait PresenceSink {
+    /// Human-readable name used in logs and status messages
+    fn name(&self) -> &'static str;
+
+    /// Connect to the output; called again after a failure
+    fn connect(&mut self) -> Result<()> {
+        Ok(())
+    }
+
+    /// Publish the current presence
+    fn update(&mut self, update: &PresenceUpdate) -> Result<()>;
+
+    /// Remove the presence, e.g. when the game closes
+    fn clear(&mut self, config: &AppConfig) -> Result<()>;
+
+    /// Hide the presence while paused. The game session goes on: `update`
+    /// resumes it and `clear` still ends it. Defaults to `clear`.
+    fn pause(&mut self, config: &AppConfig) -> Result<()> {
+        self.clear(config)
+    }
+
+    /// Send anything held back, e.g. a rate-limited clear; called on polls
+    /// that don't update the presence, such as while paused
+    fn flush(&mut self, _config: &AppConfig) -> Result<()> {
+        Ok(())
+    }
+
+    /// Release the connection, e.g. while nothing is running
+    fn disconnect(&mut self) {}
+
+    /// Release the connection for good as the app exits. Defaults to
+    /// `disconnect`.
+    fn shutdown(&mut self) {
+        self.disconnect();
+    }
+}
+
+/// A sink together with its connection and retry state
+struct SinkSlot {
+    sink: Box<dyn PresenceSink>,
+    connected: bool,
+    failures: u32,
+    retry_at: Option<Instant>,
+    /// Transitions not delivered yet
+    pending: Vec<Transition>,
+}
+
+impl SinkSlot {
+    /// Mark the sink as failed and schedule the next connection attempt
+    fn fail(&mut self) {
+        self.connected = false;
+        self.failures += 1;
+        let delay = Duration::from_millis(POLL_INTERVAL_MS)
+            .saturating_mul(2u32.saturating_pow(self.failures - 1))
+            .min(MAX_RETRY_DELAY);
+        self.retry_at = Some(Instant::now() + delay);
+    }
+
+    /// Add transitions to deliver, dropping the oldest beyond
+    /// [`MAX_PENDING_TRANSITIONS`]
+    fn queue(&mut self, transitions: &[Transition]) {
+        self.pending.extend_from_slice(transitions);
+        let excess = self.pending.len().saturating_sub(MAX_PENDING_TRANSITIONS);
+        if excess > 0 {
+            warn!("{} output missed {} state change(s)", self.sink.name(), excess);
+            self.pending.drain(..excess);
+        }
+    }
+}
+
+/// The set of enabled sinks
+#[derive(Default)]
+pub struct SinkSet {
+    slots: Vec<SinkSlot>,
+}
+
+impl SinkSet {
+    /// Create an empty sink set
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add a sink
+    pub fn push(&mut self, sink: Box<dyn PresenceSink>) {
+        info!("Enabled {} output", sink.name());
+        self.slots.push(SinkSlot {
+            sink,
+            connected: false,
+            failures: 0,
+            retry_at: None,
+            pending: Vec::new(),
+        });
+    }
+
+    /// Check if any sink is currently connected
+    pub fn any_connected(&self) -> bool {
+        self.slots.iter().any(|slot| slot.connected)
+    }
+
+    /// Connect sinks that aren't connected yet and are due for a retry.
+    /// Returns the names of sinks that are still not connected.
+    pub fn connect(&mut self) -> Vec<&'static str> {
+        let now = Instant::now();
+        let mut waiting = Vec::new();
+
+        for slot in &mut self.slots {
+            if slot.connected {
+                continue;
+            }
+            if slot.retry_at.is_some_and(|at| now < at) {
+                waiting.push(slot.sink.name());
+                continue;
+            }
+
+            match slot.sink.connect() {
+                Ok(()) => {
+                    slot.connected = true;
+                    slot.failures = 0;
+                    slot.retry_at = None;
+                }
+                Err(e) => {
+                    warn!("Could not connect {} output: {}", slot.sink.name(), e);
+                    slot.fail();
+                    waiting.push(slot.sink.name());
+                }
+            }
+        }
+
+        waiting
+    }
+
+    /// Update all connected sinks. Sinks that aren't connected keep the
+    /// transitions and get them with the first update after reconnecting;
+    /// a failed update is retried with the same transitions.
+    pub fn update(&mut self, update: &PresenceUpdate) {
+        for slot in &mut self.slots {
+            slot.queue(update.transitions);
+            if !slot.connected {
+                continue;
+            }
+
+            let transitions = std::mem::take(&mut slot.pending);
+            let result = slot.sink.update(&PresenceUpdate {
+                transitions: &transitions,
+                ..*update
+            });
+            if let Err(e) = result {
+                warn!("Failed to update {} output: {}", slot.sink.name(), e);
+                slot.sink.disconnect();
+                slot.fail();
+                slot.pending = transitions;
+            }
+        }
+    }
+
+    /// Clear the presence on all connected sinks
+    pub fn clear(&mut self, config: &AppConfig) {
+        for slot in self.slots.iter_mut().filter(|slot| slot.connected) {
+            if let Err(e) = slot.sink.clear(config) {
+                warn!("Failed to clear {} output: {}", slot.sink.name(), e);
+            }
+        }
+    }
+
+    /// Keep transitions for all sinks without updating them (e.g. while
+    /// paused); the next [`SinkSet::update`] delivers them. Connected sinks
+    /// still send what they held back.
+    pub fn hold(&mut self, transitions: &[Transition], config: &AppConfig) {
+        for slot in &mut self.slots {
+            slot.queue(transitions);
+            if slot.connected {
+                if let Err(e) = slot.sink.flush(config) {
+                    warn!("Failed to flush {} output: {}", slot.sink.name(), e);
+                }
+            }
+        }
+    }
+
+    /// Hide the presence on all connected sinks, keeping them connected
+    pub fn pause(&mut self, config: &AppConfig) {
+        for slot in self.slots.iter_mut().filter(|slot| slot.connected) {
+            if let Err(e) = slot.sink.pause(config) {
+                warn!("Failed to pause {} output: {}", slot.sink.name(), e);
+            }
+        }
+    }
+
+    /// Disconnect all connected sinks
+    pub fn disconnect(&mut self) {
+        for slot in self.slots.iter_mut().filter(|slot| slot.connected) {
+            slot.sink.disconnect();
+            slot.connected = false;
+        }
+    }
+
+    /// Shut down all connected sinks as the app exits
+    pub fn shutdown(&mut self) {
+        for slot in self.slots.iter_mut().filter(|slot| slot.connected) {
+            slot.sink.shutdown();
+            slot.connected = false;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::state_machine::Cause;
+    use std::sync::{Arc, Mutex};
+
+    /// Sink recording calls, failing to connect a given number of times
+    struct RecordingSink {
+        calls: Arc<Mutex<Vec<String>>>,
+        connect_failures: u32,
+    }
+
+    impl PresenceSink for RecordingSink {
+        fn name(&self) -> &'static str {
+            "Recording"
+        }
+
+        fn connect(&mut self) -> Result<()> {
+            if self.connect_failures > 0 {
+                self.connect_failures -= 1;
+                anyhow::bail!("unavailable");
+            }
+            self.calls.lock().unwrap().push("connect".to_string());
+            Ok(())
+        }
+
+        fn update(&mut self, update: &PresenceUpdate) -> Result<()> {
+            let mut call = format!("update {}", update.state.kind());
+            for transition in update.transitions {
+                call.push_str(&format!(" {}", transition.at));
+            }
+            self.calls.lock().unwrap().push(call);
+            Ok(())
+        }
+
+        fn clear(&mut self, _config: &AppConfig) -> Result<()> {
+            self.calls.lock().unwrap().push("clear".to_string());
+            Ok(())
+        }
+
+        fn flush(&mut self, _config: &AppConfig) -> Result<()> {
+            self.calls.lock().unwrap().push("flush".to_string());
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_failing_sink_does_not_block_others() {
+        let failing = Arc::new(Mutex::new(Vec::new()));
+        let working = Arc::new(Mutex::new(Vec::new()));
+
+        let mut sinks = SinkSet::new();
+        sinks.push(Box::new(RecordingSink {
+            calls: failing.clone(),
+            connect_failures: 10,
+        }));
+        sinks.push(Box::new(RecordingSink {
+            calls: working.clone(),
+            connect_failures: 0,
+        }));
+
+        assert_eq!(sinks.connect(), vec!["Recording"]);
+
+        let config = AppConfig::default();
+        sinks.update(&PresenceUpdate {
+            state: &GameState::MainMenu,
+            session_start: None,
+            game_running: true,
+            game_started_at: None,
+            transitions: &[],
+            resources: None,
+            health: &Health::new(),
+            config: &config,
+        });
+        sinks.clear(&config);
+
+        assert!(failing.lock().unwrap().is_empty());
+        assert_eq!(
+            *working.lock().unwrap(),
+            vec!["connect", "update main_menu", "clear"]
+        );
+
+        // The failing sink is not retried before its backoff elapses
+        assert_eq!(sinks.connect(), vec!["Recording"]);
+        assert_eq!(sinks.slots[0].failures, 1);
+    }
+
+    #[test]
+    fn test_transitions_replayed_after_reconnect() {
+        let calls = Arc::new(Mutex::new(Vec::new()));
+        let mut sinks = SinkSet::new();
+        sinks.push(Box::new(RecordingSink {
+            calls: calls.clone(),
+            connect_failures: 1,
+        }));
+        assert_eq!(sinks.connect(), vec!["Recording"]);
+
+        let config = AppConfig::default();
+        let health = Health::new();
+        let transition = |at| Transition {
+            from: GameState::Unknown,
+            to: GameState::MainMenu,
+            cause: Cause::MainMenu,
+            at,
+        };
+        let batches = [vec![transition(1)], vec![transition(2)], vec![transition(3)], vec![]];
+        for (i, transitions) in batches.iter().enumerate() {
+            // Back after two missed updates
+            if i == 2 {
+                sinks.slots[0].retry_at = None;
+                assert!(sinks.connect().is_empty());
+            }
+            sinks.update(&PresenceUpdate {
+                state: &GameState::MainMenu,
+                session_start: None,
+                game_running: true,
+                game_started_at: None,
+                transitions,
+                resources: None,
+                health: &health,
+                config: &config,
+            });
+        }
+
+        assert_eq!(
+            *calls.lock().unwrap(),
+            vec!["connect", "update main_menu 1 2 3", "update main_menu"]
+        );
+    }
+
+    #[test]
+    fn test_held_transitions_delivered_on_update() {
+        let calls = Arc::new(Mutex::new(Vec::new()));
+        let mut sinks = SinkSet::new();
+        sinks.push(Box::new(RecordingSink {
+            calls: calls.clone(),
+            connect_failures: 0,
+        }));
+        assert!(sinks.connect().is_empty());
+
+        let transition = |at| Transition {
+            from: GameState::MainMenu,
+            to: GameState::Unknown,
+            cause: Cause::GameExited,
+            at,
+        };
+        let config = AppConfig::default();
+        sinks.hold(&[transition(1)], &config);
+        sinks.hold(&[transition(2)], &config);
+        assert_eq!(*calls.lock().unwrap(), vec!["connect", "flush", "flush"]);
+
+        sinks.update(&PresenceUpdate {
+            state: &GameState::Unknown,
+            session_start: None,
+            game_running: false,
+            game_started_at: None,
+            transitions: &[transition(3)],
+            resources: None,
+            health: &Health::new(),
+            config: &config,
+        });
+        assert_eq!(
+            calls.lock().unwrap().last().unwrap(),
+            "update unknown 1 2 3"
+        );
+    }
+}
@@ -0,0 +1,251 @@
+//! Game state machine
+//!
+//! The log watcher asks for a new state along with the [`Cause`] that
+//! triggered it. The machine only accepts the transitions allowed from the
+//! current state, so a stray line (e.g. "World loaded" in the main menu)
+//! can't jump into a session, and passes accepted [`Transition`]s to the
+//! subscribed hooks when they are taken.
+//!
+//! The app subscribes the JSON event stream and the time-per-state metrics.
+//! The sinks (Discord, HTTP, webhooks, MQTT, ...) get the same transitions
+//! through [`PresenceUpdate::transitions`](crate::sink::PresenceUpdate::transitions),
+//! the tray status is refreshed when there are any, and the session history
+//! records a session when its game exits.
+
+use log::debug;
+use serde::{Deserialize, Serialize};
+
+use crate::config::GameState;
+use crate::metrics;
+
+/// What caused a state change
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Cause {
+    /// The game entered the main menu
+    MainMenu,
+    /// Connecting to or creating a singleplayer world
+    JoinWorld,
+    /// Connecting to a server
+    JoinServer,
+    /// A new stage while loading
+    LoadingStage,
+    /// The world finished loading
+    InGame,
+    /// A log line naming the mode being played
+    Playing,
+    /// The log file was replaced or truncated
+    LogReset,
+    /// The state rebuilt from a log opened mid-session
+    CatchUp,
+    /// The game client exited
+    GameExited,
+    /// An output was cleared (game closed or presence paused)
+    Cleared,
+}
+
+/// A change of game state
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Transition {
+    pub from: GameState,
+    pub to: GameState,
+    pub cause: Cause,
+    /// Unix timestamp (seconds) at which the change was observed
+    pub at: i64,
+}
+
+/// Called with every accepted transition
+pub type Hook = Box<dyn FnMut(&Transition) + Send>;
+
+/// Whether `cause` may move the game from `from` to `to`
+pub fn is_allowed(from: &GameState, to: &GameState, cause: Cause) -> bool {
+    let loading = matches!(from, GameState::Loading { .. });
+    match (cause, to) {
+        (Cause::MainMenu, GameState::MainMenu) => true,
+        (Cause::JoinWorld | Cause::JoinServer, GameState::Loading { .. }) => true,
+        (Cause::LoadingStage, GameState::Loading { .. }) => loading,
+        // Without a main menu in view (e.g. caught up mid-session) the
+        // loading lines may be missing too
+        (Cause::InGame, GameState::Singleplayer { .. } | GameState::Multiplayer { .. }) => {
+            loading || *from == GameState::Unknown
+        }
+        (Cause::Playing, GameState::Singleplayer { .. } | GameState::Multiplayer { .. }) => {
+            !matches!(from, GameState::MainMenu | GameState::Launcher)
+        }
+        (Cause::LogReset | Cause::GameExited | Cause::Cleared, GameState::Unknown) => true,
+        _ => false,
+    }
+}
+
+/// The current game state and the transitions not taken yet
+pub struct StateMachine {
+    state: GameState,
+    transitions: Vec<Transition>,
+    hooks: Vec<Hook>,
+}
+
+impl StateMachine {
+    /// Start in [`GameState::Unknown`]
+    pub fn new() -> Self {
+        Self {
+            state: GameState::Unknown,
+            transitions: Vec::new(),
+            hooks: Vec::new(),
+        }
+    }
+
+    pub fn state(&self) -> &GameState {
+        &self.state
+    }
+
+    /// Call `hook` with every transition taken from now on
+    pub fn subscribe(&mut self, hook: impl FnMut(&Transition) + Send + 'static) {
+        self.hooks.push(Box::new(hook));
+    }
+
+    /// Move to `to` if that is allowed. Returns whether the state changed.
+    pub fn apply(&mut self, to: GameState, cause: Cause, at: i64) -> bool {
+        if to == self.state {
+            return false;
+        }
+        if !is_allowed(&self.state, &to, cause) {
+            debug!(
+                "Ignoring transition from {} to {} ({:?})",
+                self.state.kind(),
+                to.kind(),
+                cause
+            );
+            metrics::global().inc_transitions_rejected();
+            return false;
+        }
+
+        let from = std::mem::replace(&mut self.state, to);
+        self.transitions.push(Transition {
+            from,
+            to: self.state.clone(),
+            cause,
+            at,
+        });
+        true
+    }
+
+    /// Position to [`squash`](Self::squash) transitions from
+    pub fn mark(&self) -> usize {
+        self.transitions.len()
+    }
+
+    /// Replace the transitions since `mark` with a single [`Cause::CatchUp`]
+    /// one to the current state. Returns whether the state changed.
+    pub fn squash(&mut self, mark: usize) -> bool {
+        let Some(first) = self.transitions.get(mark) else {
+            return false;
+        };
+        let from = first.from.clone();
+        let at = self.transitions.last().map_or(first.at, |t| t.at);
+        self.transitions.truncate(mark);
+        if from == self.state {
+            return false;
+        }
+        self.transitions.push(Transition {
+            from,
+            to: self.state.clone(),
+            cause: Cause::CatchUp,
+            at,
+        });
+        true
+    }
+
+    /// Take the transitions since the last call, passing each to the hooks
+    pub fn take(&mut self) -> Vec<Transition> {
+        let transitions = std::mem::take(&mut self.transitions);
+        for transition in &transitions {
+            for hook in &mut self.hooks {
+                hook(transition);
+            }
+        }
+        transitions
+    }
+
+    /// Go back to [`GameState::Unknown`] without a transition, keeping the hooks
+    pub fn reset(&mut self) {
+        self.state = GameState::Unknown;
+        self.transitions.clear();
+    }
+}
+
+impl Default for StateMachine {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::{Arc, Mutex};
+
+    fn singleplayer() -> GameState {
+        GameState::Singleplayer {
+            world_name: "Orbis".to_string(),
+        }
+    }
+
+    fn loading() -> GameState {
+        GameState::Loading {
+            world_name: Some("Orbis".to_string()),
+            is_multiplayer: false,
+            sub_stage: None,
+        }
+    }
+
+    #[test]
+    fn test_guards() {
+        let mut machine = StateMachine::new();
+        assert!(machine.apply(GameState::MainMenu, Cause::MainMenu, 1));
+
+        // A stray "world loaded" line in the main menu
+        assert!(!machine.apply(singleplayer(), Cause::InGame, 2));
+        assert!(!machine.apply(loading(), Cause::LoadingStage, 2));
+        assert_eq!(*machine.state(), GameState::MainMenu);
+
+        assert!(machine.apply(loading(), Cause::JoinWorld, 3));
+        assert!(machine.apply(singleplayer(), Cause::InGame, 4));
+        assert!(!machine.apply(GameState::Unknown, Cause::InGame, 5));
+        assert!(machine.apply(GameState::Unknown, Cause::GameExited, 5));
+
+        let causes: Vec<Cause> = machine.take().iter().map(|t| t.cause).collect();
+        assert_eq!(
+            causes,
+            [Cause::MainMenu, Cause::JoinWorld, Cause::InGame, Cause::GameExited]
+        );
+    }
+
+    #[test]
+    fn test_squash_and_hooks() {
+        let seen = Arc::new(Mutex::new(Vec::new()));
+        let mut machine = StateMachine::new();
+        let hook_seen = seen.clone();
+        machine.subscribe(move |t| hook_seen.lock().unwrap().push(t.to.kind()));
+
+        let mark = machine.mark();
+        machine.apply(GameState::MainMenu, Cause::MainMenu, 1);
+        machine.apply(loading(), Cause::JoinWorld, 2);
+        machine.apply(singleplayer(), Cause::InGame, 3);
+        assert!(machine.squash(mark));
+
+        let transitions = machine.take();
+        assert_eq!(transitions.len(), 1);
+        assert_eq!(transitions[0].from, GameState::Unknown);
+        assert_eq!(transitions[0].cause, Cause::CatchUp);
+        assert_eq!(transitions[0].at, 3);
+        assert_eq!(*seen.lock().unwrap(), ["singleplayer"]);
+
+        // Nothing changed overall
+        let mark = machine.mark();
+        machine.apply(GameState::MainMenu, Cause::MainMenu, 4);
+        machine.apply(loading(), Cause::JoinWorld, 5);
+        machine.apply(singleplayer(), Cause::InGame, 6);
+        assert!(!machine.squash(mark));
+        assert!(machine.take().is_empty());
+    }
+}
@@ -1,15 +1,12 @@
 //! System tray UI module
 
-use std::sync::mpsc::{self, Receiver, Sender};
-use std::sync::{Arc, Mutex};
-
-use anyhow::Result;
-use log::{debug, error, info};
-
-use crate::config::AppConfig;
+#[cfg(feature = "notifications")]
+use log::error;
 
 /// Events from the tray menu
 #[derive(Debug, Clone)]
+// Without the `tray` feature there is no menu to send them
+#[cfg_attr(not(feature = "tray"), allow(dead_code))]
 pub enum TrayEvent {
     Quit,
     OpenGithub,
@@ -19,15 +16,21 @@ pub enum TrayEvent {
 }
 
 /// Status to display in tray
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub struct TrayStatus {
     pub tooltip: String,
+    /// Label of the health submenu, e.g. `Health: 1 error, 2 warnings`
+    pub health_label: String,
+    /// Entries of the health submenu
+    pub health: Vec<String>,
 }
 
 impl Default for TrayStatus {
     fn default() -> Self {
         Self {
             tooltip: "Waiting for Hytale...".to_string(),
+            health_label: "Health: no warnings or errors".to_string(),
+            health: Vec::new(),
         }
     }
 }
@@ -36,13 +39,20 @@ impl Default for TrayStatus {
 // Linux implementation using ksni (StatusNotifierItem)
 // ============================================================================
 
-#[cfg(target_os = "linux")]
+#[cfg(all(feature = "tray", target_os = "linux"))]
 mod linux {
+    use std::sync::mpsc::{self, Receiver, Sender};
+    use std::sync::{Arc, Mutex};
+
+    use anyhow::Result;
+    use log::{debug, info};
+
     use super::*;
+    use crate::config::AppConfig;
     use ksni::{self, Tray, TrayService};
 
     struct HytaleTray {
-        status: Arc<Mutex<String>>,
+        status: Arc<Mutex<TrayStatus>>,
         config: Arc<Mutex<AppConfig>>,
         event_tx: Sender<TrayEvent>,
     }
@@ -62,7 +72,7 @@ mod linux {
         }
 
         fn tool_tip(&self) -> ksni::ToolTip {
-            let status = self.status.lock().unwrap().clone();
+            let status = self.status.lock().unwrap().tooltip.clone();
             ksni::ToolTip {
                 title: "Hytale Discord RPC".to_string(),
                 description: status,
@@ -77,13 +87,32 @@ mod linux {
             let status = self.status.lock().unwrap().clone();
             let config = self.config.lock().unwrap();
 
+            let health = status
+                .health
+                .into_iter()
+                .map(|label| {
+                    StandardItem {
+                        label,
+                        enabled: false,
+                        ..Default::default()
+                    }
+                    .into()
+                })
+                .collect();
+
             vec![
                 StandardItem {
-                    label: status,
+                    label: status.tooltip,
                     enabled: false,
                     ..Default::default()
                 }
                 .into(),
+                SubMenu {
+                    label: status.health_label,
+                    submenu: health,
+                    ..Default::default()
+                }
+                .into(),
                 MenuItem::Separator,
                 CheckmarkItem {
                     label: "Show World Name".to_string(),
@@ -135,14 +164,14 @@ mod linux {
 
     pub struct SystemTray {
         event_rx: Receiver<TrayEvent>,
-        status: Arc<Mutex<String>>,
+        status: Arc<Mutex<TrayStatus>>,
         handle: ksni::Handle<HytaleTray>,
     }
 
     impl SystemTray {
         pub fn new(config: Arc<Mutex<AppConfig>>) -> Result<Self> {
             let (event_tx, event_rx) = mpsc::channel();
-            let status = Arc::new(Mutex::new("Waiting for Hytale...".to_string()));
+            let status = Arc::new(Mutex::new(TrayStatus::default()));
 
             let tray = HytaleTray {
                 status: status.clone(),
@@ -169,7 +198,7 @@ mod linux {
 
         pub fn update_status(&self, new_status: TrayStatus) {
             if let Ok(mut status) = self.status.lock() {
-                *status = new_status.tooltip.clone();
+                *status = new_status.clone();
             }
             // Trigger tray update
             self.handle.update(|_| {});
@@ -187,11 +216,20 @@ mod linux {
 // macOS/Windows implementation using tray-icon
 // ============================================================================
 
-#[cfg(any(target_os = "macos", target_os = "windows"))]
+#[cfg(all(feature = "tray", any(target_os = "macos", target_os = "windows")))]
 mod desktop {
+    use std::sync::mpsc::{self, Receiver, Sender};
+    use std::sync::{Arc, Mutex};
+
+    use anyhow::Result;
+    use log::{debug, info};
+
     use super::*;
+    use crate::config::AppConfig;
     use image::RgbaImage;
-    use tray_icon::menu::{CheckMenuItem, Menu, MenuEvent, MenuItem, PredefinedMenuItem};
+    use std::cell::RefCell;
+
+    use tray_icon::menu::{CheckMenuItem, Menu, MenuEvent, MenuItem, PredefinedMenuItem, Submenu};
     use tray_icon::{Icon, TrayIcon, TrayIconBuilder};
 
     pub struct SystemTray {
@@ -199,6 +237,8 @@ mod desktop {
         event_rx: Receiver<TrayEvent>,
         status: Arc<Mutex<TrayStatus>>,
         status_item: MenuItem,
+        health_menu: Submenu,
+        health_items: RefCell<Vec<MenuItem>>,
         world_name_item: CheckMenuItem,
         server_ip_item: CheckMenuItem,
     }
@@ -215,6 +255,7 @@ mod desktop {
             };
 
             let status_item = MenuItem::new("Waiting for Hytale...", false, None);
+            let health_menu = Submenu::new(TrayStatus::default().health_label, true);
             let separator = PredefinedMenuItem::separator();
             let world_name_item = CheckMenuItem::new("Show World Name", true, show_world_name, None);
             let server_ip_item = CheckMenuItem::new("Show Server IP", true, show_server_ip, None);
@@ -226,6 +267,7 @@ mod desktop {
 
             let menu = Menu::new();
             menu.append(&status_item)?;
+            menu.append(&health_menu)?;
             menu.append(&separator)?;
             menu.append(&world_name_item)?;
             menu.append(&server_ip_item)?;
@@ -282,6 +324,8 @@ mod desktop {
                 event_rx,
                 status,
                 status_item,
+                health_menu,
+                health_items: RefCell::new(Vec::new()),
                 world_name_item,
                 server_ip_item,
             })
@@ -292,10 +336,27 @@ mod desktop {
         }
 
         pub fn update_status(&self, new_status: TrayStatus) {
-            if let Ok(mut status) = self.status.lock() {
-                *status = new_status.clone();
-            }
+            let health_changed = match self.status.lock() {
+                Ok(mut status) => {
+                    let changed = status.health != new_status.health;
+                    *status = new_status.clone();
+                    changed
+                }
+                Err(_) => false,
+            };
             let _ = self.status_item.set_text(&new_status.tooltip);
+            let _ = self.health_menu.set_text(&new_status.health_label);
+            if health_changed {
+                let mut items = self.health_items.borrow_mut();
+                for item in items.drain(..) {
+                    let _ = self.health_menu.remove(&item);
+                }
+                for label in &new_status.health {
+                    let item = MenuItem::new(label, false, None);
+                    let _ = self.health_menu.append(&item);
+                    items.push(item);
+                }
+            }
             debug!("Tray status updated: {}", new_status.tooltip);
         }
 
@@ -336,21 +397,60 @@ mod desktop {
     }
 }
 
+// ============================================================================
+// Fallback when built without the `tray` feature
+// ============================================================================
+
+#[cfg(not(feature = "tray"))]
+mod headless {
+    use std::sync::{Arc, Mutex};
+
+    use anyhow::Result;
+
+    use super::*;
+    use crate::config::AppConfig;
+
+    /// Stand-in tray that can never be created
+    pub struct SystemTray;
+
+    impl SystemTray {
+        pub fn new(_config: Arc<Mutex<AppConfig>>) -> Result<Self> {
+            anyhow::bail!("built without the `tray` feature")
+        }
+
+        pub fn poll_event(&self) -> Option<TrayEvent> {
+            None
+        }
+
+        pub fn update_status(&self, _new_status: TrayStatus) {}
+
+        pub fn refresh_menu(&self) {}
+    }
+}
+
 // ============================================================================
 // Re-export the platform-specific SystemTray
 // ============================================================================
 
-#[cfg(target_os = "linux")]
+#[cfg(all(feature = "tray", target_os = "linux"))]
 pub use linux::SystemTray;
 
-#[cfg(any(target_os = "macos", target_os = "windows"))]
+#[cfg(all(feature = "tray", any(target_os = "macos", target_os = "windows")))]
 pub use desktop::SystemTray;
 
+#[cfg(not(feature = "tray"))]
+pub use headless::SystemTray;
+
 // ============================================================================
 // Cross-platform utilities
 // ============================================================================
 
+/// Show a system notification (does nothing without the `notifications` feature)
+#[cfg(not(feature = "notifications"))]
+pub fn show_notification(_title: &str, _body: &str) {}
+
 /// Show a system notification
+#[cfg(feature = "notifications")]
 pub fn show_notification(title: &str, body: &str) {
     #[cfg(target_os = "linux")]
     {
@@ -0,0 +1,598 @@
+//! Outgoing webhook notifications for session events
+//!
+//! Payloads are handed to a background worker that delivers them in order,
+//! retrying with exponential backoff. Undelivered payloads are kept in an
+//! on-disk queue so they survive network outages and restarts.
+
+use std::collections::VecDeque;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError, Sender};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use anyhow::Result;
+use log::{debug, info, warn};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+
+use crate::config::{get_data_dir, AppConfig, GameState, WebhookEvent, WebhookFormat};
+use crate::file_output::write_atomic;
+use crate::log_watcher::unix_now;
+use crate::sink::{PresenceSink, PresenceUpdate};
+use crate::state_machine::Transition;
+
+/// File name of the on-disk delivery queue
+const QUEUE_FILE: &str = "webhook-queue.json";
+
+/// Maximum number of payloads kept while the endpoint is unreachable
+const MAX_QUEUED: usize = 100;
+
+/// Timeout for a single delivery attempt
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Exponential backoff between delivery attempts
+#[derive(Debug, Clone, Copy)]
+struct RetryPolicy {
+    base: Duration,
+    max: Duration,
+}
+
+impl RetryPolicy {
+    fn delay(&self, attempts: u32) -> Duration {
+        let factor = 2u32.saturating_pow(attempts.saturating_sub(1));
+        self.base.saturating_mul(factor).min(self.max)
+    }
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            base: Duration::from_secs(2),
+            max: Duration::from_secs(300),
+        }
+    }
+}
+
+/// A payload waiting to be delivered
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct Delivery {
+    url: String,
+    body: Value,
+    #[serde(default)]
+    attempts: u32,
+}
+
+/// Why a delivery attempt failed
+enum Failure {
+    /// Worth trying again later (network error, 5xx, 429)
+    Retry(String),
+    /// The endpoint rejected the payload; retrying won't help
+    Reject(String),
+}
+
+/// Background worker delivering queued payloads
+struct Worker {
+    rx: Receiver<Delivery>,
+    queue: VecDeque<Delivery>,
+    queue_path: PathBuf,
+    retry: RetryPolicy,
+    agent: ureq::Agent,
+    next_attempt: Instant,
+}
+
+impl Worker {
+    fn run(mut self) {
+        loop {
+            if Instant::now() >= self.next_attempt {
+                self.flush();
+            }
+
+            let received = if self.queue.is_empty() {
+                match self.rx.recv() {
+                    Ok(delivery) => delivery,
+                    Err(_) => return,
+                }
+            } else {
+                let wait = self.next_attempt.saturating_duration_since(Instant::now());
+                match self.rx.recv_timeout(wait) {
+                    Ok(delivery) => delivery,
+                    Err(RecvTimeoutError::Timeout) => continue,
+                    Err(RecvTimeoutError::Disconnected) => return,
+                }
+            };
+            self.enqueue(received);
+        }
+    }
+
+    fn enqueue(&mut self, delivery: Delivery) {
+        if self.queue.len() >= MAX_QUEUED {
+            warn!("Webhook queue full, dropping oldest payload");
+            self.queue.pop_front();
+        }
+        self.queue.push_back(delivery);
+        self.persist();
+    }
+
+    /// Deliver queued payloads in order until one fails
+    fn flush(&mut self) {
+        if self.queue.is_empty() {
+            return;
+        }
+
+        while let Some(delivery) = self.queue.front_mut() {
+            match send(&self.agent, delivery) {
+                Ok(()) => {
+                    debug!("Webhook delivered to {}", delivery.url);
+                    self.queue.pop_front();
+                }
+                Err(Failure::Reject(e)) => {
+                    warn!("Webhook rejected ({}), dropping payload", e);
+                    self.queue.pop_front();
+                }
+                Err(Failure::Retry(e)) => {
+                    delivery.attempts += 1;
+                    let delay = self.retry.delay(delivery.attempts);
+                    warn!("Webhook delivery failed ({}), retrying in {:?}", e, delay);
+                    self.next_attempt = Instant::now() + delay;
+                    break;
+                }
+            }
+        }
+
+        self.persist();
+    }
+
+    /// Mirror the in-memory queue to disk
+    fn persist(&self) {
+        let result = if self.queue.is_empty() {
+            match fs::remove_file(&self.queue_path) {
+                Err(e) if e.kind() != std::io::ErrorKind::NotFound => Err(e),
+                _ => Ok(()),
+            }
+        } else {
+            serde_json::to_vec(&self.queue)
+                .map_err(std::io::Error::from)
+                .and_then(|data| write_atomic(&self.queue_path, &data))
+        };
+
+        if let Err(e) = result {
+            warn!("Failed to persist webhook queue: {}", e);
+        }
+    }
+}
+
+fn send(agent: &ureq::Agent, delivery: &Delivery) -> Result<(), Failure> {
+    match agent.post(&delivery.url).send_json(&delivery.body) {
+        Ok(_) => Ok(()),
+        Err(ureq::Error::Status(code, _)) if code == 408 || code == 429 || code >= 500 => {
+            Err(Failure::Retry(format!("HTTP {}", code)))
+        }
+        Err(ureq::Error::Status(code, _)) => Err(Failure::Reject(format!("HTTP {}", code))),
+        Err(e) => Err(Failure::Retry(e.to_string())),
+    }
+}
+
+fn load_queue(path: &Path) -> VecDeque<Delivery> {
+    let Ok(data) = fs::read(path) else {
+        return VecDeque::new();
+    };
+    match serde_json::from_slice::<VecDeque<Delivery>>(&data) {
+        Ok(queue) => {
+            if !queue.is_empty() {
+                info!("Loaded {} pending webhook(s) from {}", queue.len(), path.display());
+            }
+            queue
+        }
+        Err(e) => {
+            warn!("Ignoring unreadable webhook queue {}: {}", path.display(), e);
+            VecDeque::new()
+        }
+    }
+}
+
+/// Webhook sender turning game events into queued deliveries
+pub struct WebhookSink {
+    tx: Sender<Delivery>,
+    game_started_at: Option<i64>,
+    /// Server currently being played on, with the time it was joined
+    server: Option<(GameState, i64)>,
+}
+
+impl WebhookSink {
+    /// Create a webhook sink using the queue in the data directory
+    pub fn new() -> Self {
+        Self::with_options(get_data_dir().join(QUEUE_FILE), RetryPolicy::default())
+    }
+
+    fn with_options(queue_path: PathBuf, retry: RetryPolicy) -> Self {
+        let (tx, rx) = mpsc::channel();
+        let worker = Worker {
+            rx,
+            queue: load_queue(&queue_path),
+            queue_path,
+            retry,
+            agent: ureq::AgentBuilder::new().timeout(REQUEST_TIMEOUT).build(),
+            next_attempt: Instant::now(),
+        };
+        thread::spawn(move || worker.run());
+
+        Self {
+            tx,
+            game_started_at: None,
+            server: None,
+        }
+    }
+
+    /// Feed the game status and new transitions, sending webhooks for the selected events.
+    /// `started_at` is the game process start, so a session already under way
+    /// when the app starts still gets its full duration.
+    fn notify(
+        &mut self,
+        game_running: bool,
+        started_at: Option<i64>,
+        transitions: &[Transition],
+        config: &AppConfig,
+    ) {
+        if game_running && self.game_started_at.is_none() {
+            let started_at = started_at.unwrap_or_else(unix_now);
+            self.game_started_at = Some(started_at);
+            self.emit(WebhookEvent::GameStart, &GameState::Unknown, None, started_at, config);
+        }
+
+        for transition in transitions {
+            let on_server = matches!(transition.to, GameState::Multiplayer { .. });
+            let same_server = self.server.as_ref().is_some_and(|(server, _)| {
+                on_server && server.server_address() == transition.to.server_address()
+            });
+
+            if !same_server {
+                self.leave_server(transition.at, config);
+            }
+            if on_server && !same_server {
+                self.server = Some((transition.to.clone(), transition.at));
+                self.emit(WebhookEvent::ServerJoin, &transition.to, None, transition.at, config);
+            }
+        }
+    }
+
+    fn leave_server(&mut self, at: i64, config: &AppConfig) {
+        if let Some((server, joined_at)) = self.server.take() {
+            self.emit(WebhookEvent::ServerLeave, &server, Some(at - joined_at), at, config);
+        }
+    }
+
+    fn end_session(&mut self, config: &AppConfig) {
+        let now = unix_now();
+        self.leave_server(now, config);
+        if let Some(started_at) = self.game_started_at.take() {
+            self.emit(WebhookEvent::SessionEnd, &GameState::Unknown, Some(now - started_at), now, config);
+        }
+    }
+
+    fn emit(
+        &self,
+        event: WebhookEvent,
+        state: &GameState,
+        duration_secs: Option<i64>,
+        at: i64,
+        config: &AppConfig,
+    ) {
+        let webhook = &config.webhook;
+        if !webhook.events.contains(&event) {
+            return;
+        }
+        let Some(url) = webhook.url.clone().filter(|u| !u.is_empty()) else {
+            debug!("Webhook enabled but no URL configured");
+            return;
+        };
+
+        let body = match webhook.format {
+            WebhookFormat::Json => json_payload(event, state, duration_secs, at, config),
+            WebhookFormat::Discord => discord_payload(event, state, duration_secs, config),
+        };
+        debug!("Queueing webhook {:?}", event);
+        let _ = self.tx.send(Delivery {
+            url,
+            body,
+            attempts: 0,
+        });
+    }
+}
+
+impl PresenceSink for WebhookSink {
+    fn name(&self) -> &'static str {
+        "Webhook"
+    }
+
+    fn update(&mut self, update: &PresenceUpdate) -> Result<()> {
+        self.notify(
+            update.game_running,
+            update.game_started_at,
+            update.transitions,
+            update.config,
+        );
+        Ok(())
+    }
+
+    fn clear(&mut self, config: &AppConfig) -> Result<()> {
+        self.end_session(config);
+        Ok(())
+    }
+
+    /// Webhooks report session events, so there is nothing to hide
+    fn pause(&mut self, _config: &AppConfig) -> Result<()> {
+        Ok(())
+    }
+}
+
+impl Default for WebhookSink {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+fn json_payload(
+    event: WebhookEvent,
+    state: &GameState,
+    duration_secs: Option<i64>,
+    at: i64,
+    config: &AppConfig,
+) -> Value {
+    let server = match state {
+        GameState::Multiplayer { .. } if config.show_server_ip => json!({
+            "address": state.server_address(),
+            "name": state.server_name(),
+        }),
+        _ => Value::Null,
+    };
+
+    json!({
+        "event": event,
+        "at": at,
+        "server": server,
+        "duration_secs": duration_secs,
+    })
+}
+
+fn discord_payload(
+    event: WebhookEvent,
+    state: &GameState,
+    duration_secs: Option<i64>,
+    config: &AppConfig,
+) -> Value {
+    let who = config
+        .webhook
+        .display_name
+        .as_deref()
+        .filter(|n| !n.is_empty())
+        .unwrap_or("Someone");
+    let server = state
+        .shown_server(config)
+        .map(|s| format!("**{}**", s))
+        .unwrap_or_else(|| "a server".to_string());
+    let duration = duration_secs
+        .map(|d| format!(" after {}", format_duration(d)))
+        .unwrap_or_default();
+
+    let content = match event {
+        WebhookEvent::GameStart => format!("{} launched Hytale", who),
+        WebhookEvent::ServerJoin => format!("{} joined {}", who, server),
+        WebhookEvent::ServerLeave => format!("{} left {}{}", who, server, duration),
+        WebhookEvent::SessionEnd => format!("{} stopped playing Hytale{}", who, duration),
+    };
+
+    json!({
+        "username": "Hytale RPC",
+        "content": content,
+    })
+}
+
+/// Format a duration in seconds as e.g. "1h 5m", "12m" or "40s"
+fn format_duration(secs: i64) -> String {
+    let secs = secs.max(0);
+    let (hours, minutes) = (secs / 3600, (secs % 3600) / 60);
+    if hours > 0 {
+        format!("{}h {}m", hours, minutes)
+    } else if minutes > 0 {
+        format!("{}m", minutes)
+    } else {
+        format!("{}s", secs)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::state_machine::Cause;
+    use std::io::{BufRead, BufReader, Read, Write};
+    use std::net::TcpListener;
+
+    /// Minimal HTTP stand-in answering with the given status codes in order
+    fn spawn_stand_in(statuses: Vec<u16>) -> (String, Receiver<Value>) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}/hook", listener.local_addr().unwrap());
+        let (tx, rx) = mpsc::channel();
+
+        thread::spawn(move || {
+            for status in statuses {
+                let (stream, _) = listener.accept().unwrap();
+                let mut reader = BufReader::new(stream);
+                let mut content_length = 0;
+                loop {
+                    let mut line = String::new();
+                    reader.read_line(&mut line).unwrap();
+                    if line.trim().is_empty() {
+                        break;
+                    }
+                    if let Some((name, value)) = line.split_once(':') {
+                        if name.eq_ignore_ascii_case("content-length") {
+                            content_length = value.trim().parse().unwrap();
+                        }
+                    }
+                }
+                let mut body = vec![0; content_length];
+                reader.read_exact(&mut body).unwrap();
+
+                let response = format!(
+                    "HTTP/1.1 {} Stand-in\r\nContent-Length: 0\r\nConnection: close\r\n\r\n",
+                    status
+                );
+                reader.get_mut().write_all(response.as_bytes()).unwrap();
+                tx.send(serde_json::from_slice(&body).unwrap()).unwrap();
+            }
+        });
+
+        (url, rx)
+    }
+
+    #[test]
+    fn test_discord_payload() {
+        let mut config = AppConfig::default();
+        config.webhook.display_name = Some("Steve".to_string());
+        let state = GameState::Multiplayer {
+            server_address: Some("smp.example.com:5520".to_string()),
+            server_name: Some("Example SMP".to_string()),
+        };
+
+        let body = discord_payload(WebhookEvent::ServerJoin, &state, None, &config);
+        assert_eq!(body["content"], "Steve joined **Example SMP**");
+
+        config.show_server_ip = false;
+        let body = discord_payload(WebhookEvent::ServerLeave, &state, Some(3900), &config);
+        assert_eq!(body["content"], "Steve left a server after 1h 5m");
+    }
+
+    #[test]
+    fn test_session_events() {
+        let (tx, rx) = mpsc::channel();
+        let mut sink = WebhookSink {
+            tx,
+            game_started_at: None,
+            server: None,
+        };
+        let mut config = AppConfig::default();
+        config.webhook.url = Some("http://127.0.0.1/hook".to_string());
+
+        let server = GameState::Multiplayer {
+            server_address: Some("smp.example.com:5520".to_string()),
+            server_name: None,
+        };
+        let transitions = [
+            Transition {
+                from: GameState::MainMenu,
+                to: server.clone(),
+                cause: Cause::InGame,
+                at: 100,
+            },
+            Transition {
+                from: server,
+                to: GameState::MainMenu,
+                cause: Cause::MainMenu,
+                at: 160,
+            },
+        ];
+        sink.notify(true, None, &transitions, &config);
+        sink.clear(&config).unwrap();
+
+        let events: Vec<Value> = rx.try_iter().map(|d| d.body).collect();
+        let names: Vec<&str> = events.iter().map(|e| e["event"].as_str().unwrap()).collect();
+        assert_eq!(names, ["game_start", "server_join", "server_leave", "session_end"]);
+        assert_eq!(events[1]["server"]["address"], "smp.example.com:5520");
+        assert_eq!(events[2]["duration_secs"], 60);
+    }
+
+    #[test]
+    fn test_session_started_before_app() {
+        let (tx, rx) = mpsc::channel();
+        let mut sink = WebhookSink {
+            tx,
+            game_started_at: None,
+            server: None,
+        };
+        let mut config = AppConfig::default();
+        config.webhook.url = Some("http://127.0.0.1/hook".to_string());
+
+        let started_at = unix_now() - 3600;
+        sink.notify(true, Some(started_at), &[], &config);
+        sink.clear(&config).unwrap();
+
+        let events: Vec<Value> = rx.try_iter().map(|d| d.body).collect();
+        assert_eq!(events[0]["event"], "game_start");
+        assert_eq!(events[0]["at"], started_at);
+        assert_eq!(events[1]["event"], "session_end");
+        assert!(events[1]["duration_secs"].as_i64().unwrap() >= 3600);
+    }
+
+    #[test]
+    fn test_pause_keeps_session() {
+        let (tx, rx) = mpsc::channel();
+        let mut sink = WebhookSink {
+            tx,
+            game_started_at: None,
+            server: None,
+        };
+        let mut config = AppConfig::default();
+        config.webhook.url = Some("http://127.0.0.1/hook".to_string());
+
+        sink.notify(true, None, &[], &config);
+        sink.pause(&config).unwrap();
+        sink.notify(true, None, &[], &config);
+        sink.clear(&config).unwrap();
+
+        let names: Vec<String> = rx
+            .try_iter()
+            .map(|d| d.body["event"].as_str().unwrap().to_string())
+            .collect();
+        assert_eq!(names, ["game_start", "session_end"]);
+    }
+
+    #[test]
+    fn test_retry_until_delivered() {
+        let dir = tempfile::tempdir().unwrap();
+        let queue_path = dir.path().join(QUEUE_FILE);
+        let (url, received) = spawn_stand_in(vec![503, 204]);
+
+        let mut config = AppConfig::default();
+        config.webhook.enabled = true;
+        config.webhook.url = Some(url);
+
+        let retry = RetryPolicy {
+            base: Duration::from_millis(20),
+            max: Duration::from_millis(100),
+        };
+        let mut sink = WebhookSink::with_options(queue_path.clone(), retry);
+        sink.notify(true, None, &[], &config);
+
+        let first = received.recv_timeout(Duration::from_secs(5)).unwrap();
+        let second = received.recv_timeout(Duration::from_secs(5)).unwrap();
+        assert_eq!(first, second);
+        assert_eq!(second["event"], "game_start");
+
+        // The queue file is removed once everything is delivered
+        let deadline = Instant::now() + Duration::from_secs(5);
+        while queue_path.exists() && Instant::now() < deadline {
+            thread::sleep(Duration::from_millis(10));
+        }
+        assert!(!queue_path.exists());
+    }
+
+    #[test]
+    fn test_queue_survives_restart() {
+        let dir = tempfile::tempdir().unwrap();
+        let queue_path = dir.path().join(QUEUE_FILE);
+        let (url, received) = spawn_stand_in(vec![200]);
+
+        // Left over from a previous run while the network was down
+        let pending = vec![Delivery {
+            url,
+            body: json!({ "event": "session_end" }),
+            attempts: 3,
+        }];
+        fs::write(&queue_path, serde_json::to_vec(&pending).unwrap()).unwrap();
+
+        let _sink = WebhookSink::with_options(queue_path, RetryPolicy::default());
+        let body = received.recv_timeout(Duration::from_secs(5)).unwrap();
+        assert_eq!(body["event"], "session_end");
+    }
+}
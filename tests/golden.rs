@@ -0,0 +1,119 @@
+//! Golden-file tests: replay the logs in `tests/fixtures` and compare the
+//! state timelines with the `.expected` files next to them.
+//!
+//! The corpus is synthetic for now: the logs are written by hand in the
+//! client's log format, from the patterns the watcher already knows, so
+//! they guard against regressions in the watcher but not against changes
+//! in real client output. Real sessions run through `hytale-rpc anonymize`
+//! should replace them.
+//!
+//! After changing the patterns on purpose, update the expectations with
+//! `BLESS=1 cargo test --test golden` and review the diff.
+
+use std::fmt::Write;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use hytale_rpc::internal::{analyze_exit, anonymize_file, render_timeline, timeline as replay_timeline};
+use hytale_rpc::LogWatcher;
+
+/// Replay a log and render its transitions and how the session ended
+fn timeline(log: &Path) -> String {
+    let mut watcher = LogWatcher::new();
+    watcher.replay(log).unwrap();
+
+    let mut out = String::new();
+    for line in render_timeline(&watcher.drain_transitions(), true) {
+        let _ = writeln!(out, "{}", line);
+    }
+
+    let analysis = analyze_exit(&watcher.recent_lines(), watcher.shutdown_seen(), Vec::new());
+    let exit = if analysis.crashed {
+        format!("crashed: {}", analysis.headline.unwrap_or_default())
+    } else if watcher.shutdown_seen() {
+        "clean".to_string()
+    } else {
+        "no shutdown message".to_string()
+    };
+    let _ = writeln!(out, "exit: {}", exit);
+    out
+}
+
+fn fixtures() -> Vec<PathBuf> {
+    let dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures");
+    let mut logs: Vec<PathBuf> = fs::read_dir(dir)
+        .unwrap()
+        .map(|entry| entry.unwrap().path())
+        .filter(|path| path.extension().is_some_and(|ext| ext == "log"))
+        .collect();
+    logs.sort();
+    logs
+}
+
+/// Describe the first line where two timelines differ
+fn first_difference(expected: &str, actual: &str) -> String {
+    let mut expected_lines = expected.lines();
+    let mut actual_lines = actual.lines();
+    for line in 1.. {
+        match (expected_lines.next(), actual_lines.next()) {
+            (None, None) => break,
+            (e, a) if e == a => continue,
+            (e, a) => {
+                return format!(
+                    "line {}:\n  expected: {}\n  actual:   {}",
+                    line,
+                    e.unwrap_or("<end>"),
+                    a.unwrap_or("<end>")
+                )
+            }
+        }
+    }
+    String::new()
+}
+
+#[test]
+fn test_golden_timelines() {
+    let bless = std::env::var_os("BLESS").is_some();
+    let logs = fixtures();
+    assert!(!logs.is_empty(), "no fixtures found");
+
+    let mut failures = Vec::new();
+    for log in &logs {
+        let expected_path = log.with_extension("expected");
+        let actual = timeline(log);
+        if bless {
+            fs::write(&expected_path, &actual).unwrap();
+            continue;
+        }
+
+        let expected = fs::read_to_string(&expected_path).unwrap_or_default();
+        if expected != actual {
+            failures.push(format!(
+                "{}: {}",
+                log.file_name().unwrap().to_string_lossy(),
+                first_difference(&expected, &actual)
+            ));
+        }
+    }
+
+    assert!(
+        failures.is_empty(),
+        "timelines differ from the expected files (run with BLESS=1 to update them):\n{}",
+        failures.join("\n")
+    );
+}
+
+#[test]
+fn test_anonymized_fixtures_keep_timelines() {
+    let dir = tempfile::tempdir().unwrap();
+    for log in fixtures() {
+        let output = dir.path().join(log.file_name().unwrap());
+        anonymize_file(&log, &output).unwrap();
+        assert_eq!(
+            replay_timeline(&output, false).unwrap(),
+            replay_timeline(&log, false).unwrap(),
+            "{}",
+            log.display()
+        );
+    }
+}